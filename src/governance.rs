@@ -30,143 +30,1610 @@ extern crate srml_support as runtime_support;
 extern crate sr_primitives as runtime_primitives;
 extern crate sr_io as runtime_io;
 extern crate srml_system as system;
+extern crate srml_balances as balances;
 
 use rstd::prelude::*;
-use system::ensure_signed;
-use runtime_support::{StorageValue, StorageMap, Parameter};
+use rstd::marker::PhantomData;
+use system::{ensure_signed, ensure_root};
+use runtime_support::{StorageValue, StorageMap, Parameter, Dispatchable};
 use runtime_support::dispatch::Result;
-use runtime_primitives::traits::Hash;
-use codec::Encode;
+use runtime_primitives::traits::{Hash, Zero, As, IntegerSquareRoot};
+use runtime_primitives::Permill;
+use codec::{Encode, Decode};
+use primitives::storage::well_known_keys;
+use vote_threshold::{VoteThreshold, Approved};
 
 #[cfg_attr(feature = "std", derive(Debug))]
 #[derive(Encode, Decode, PartialEq, Clone, Copy)]
 pub enum ProposalStage {
     PreVoting,
     Voting,
+    /// Voters on a secret ballot reveal the votes they committed to
+    Revealing,
     Completed,
+    Cancelled,
+    /// Taken back by its author before voting opened
+    Withdrawn,
+    /// Stopped by the runtime's veto origin
+    Vetoed,
 }
 
 #[cfg_attr(feature = "std", derive(Debug))]
-#[derive(Encode, Decode, PartialEq, Clone, Copy)]
-pub enum ProposalCategory {
+#[derive(Encode, Decode, PartialEq, Clone)]
+pub enum ProposalCategory<AccountId, Balance, Hash> {
     Signaling,
-    Funding(u32), // TODO: convert this into a Balance
+    /// Pay the amount to the beneficiary from the treasury account if passed
+    Funding(Balance, AccountId),
+    /// Replace the runtime with the code whose preimage has this hash if passed
+    Upgrade(Hash),
+    /// Dispatch the encoded runtime call with root origin if passed
+    Call(Vec<u8>),
+}
+
+/// Where the body of a proposal can be found.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, PartialEq, Eq, Clone)]
+pub enum ProposalContents<Hash> {
+    /// A short body stored with the proposal itself
+    Inline(Vec<u8>),
+    /// The hash of a body noted with `note_preimage`
+    Preimage(Hash),
+    /// A document stored off-chain at a URI, with its hash so that it can be verified
+    Uri(Hash, Vec<u8>),
+    /// A document stored on IPFS, by its CID
+    Ipfs(Vec<u8>),
+}
+
+/// The longest preimage that may be noted, enough for a runtime's wasm code.
+pub const MAX_PREIMAGE_LEN: usize = 4 * 1024 * 1024;
+/// The longest body that may be stored inline with a proposal.
+pub const MAX_INLINE_CONTENTS_LEN: usize = 1024;
+/// The longest URI a proposal may refer to its body by.
+pub const MAX_URI_LEN: usize = 512;
+/// The longest IPFS CID a proposal may refer to its body by.
+pub const MAX_CID_LEN: usize = 128;
+/// The shortest base32 CIDv1, which carries a 256-bit digest such as sha2-256.
+pub const MIN_CIDV1_LEN: usize = 59;
+
+/// Whether a URI has a scheme, made of a letter followed by letters, digits,
+/// `+`, `-` or `.`, then a colon and a non-empty rest of printable ASCII.
+fn is_valid_uri(uri: &[u8]) -> bool {
+    if uri.len() > MAX_URI_LEN {
+        return false;
+    }
+    let colon = match uri.iter().position(|&c| c == b':') {
+        Some(colon) => colon,
+        None => return false,
+    };
+    let (scheme, rest) = (&uri[..colon], &uri[colon + 1..]);
+    !scheme.is_empty() && scheme[0].is_ascii_alphabetic()
+        && scheme.iter().all(|&c| c.is_ascii_alphanumeric() || c == b'+' || c == b'-' || c == b'.')
+        && !rest.is_empty() && rest.iter().all(|c| c.is_ascii_graphic())
+}
+
+/// Whether a CID is a CIDv0 in base58btc or a CIDv1 in base32, the encodings
+/// IPFS uses by default. The version byte of a CIDv1 always encodes to `a`
+/// after the `b` multibase prefix.
+fn is_valid_cid(cid: &[u8]) -> bool {
+    const BASE58: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    const BASE32: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+    match cid.first() {
+        Some(&b'Q') => cid.len() == 46 && cid[1] == b'm' && cid.iter().all(|c| BASE58.contains(c)),
+        Some(&b'b') => cid.len() >= MIN_CIDV1_LEN && cid.len() <= MAX_CID_LEN && cid[1] == b'a'
+            && cid[1..].iter().all(|c| BASE32.contains(c)),
+        _ => false,
+    }
+}
+
+/// The kind of a proposal, independent of the action it carries.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy)]
+pub enum ProposalKind {
+    Signaling,
+    Funding,
     Upgrade,
+    Call,
+}
+
+impl ProposalKind {
+    /// Every kind of proposal.
+    pub const ALL: [ProposalKind; 4] = [ProposalKind::Signaling, ProposalKind::Funding, ProposalKind::Upgrade, ProposalKind::Call];
+}
+
+impl<AccountId, Balance, Hash> ProposalCategory<AccountId, Balance, Hash> {
+    pub fn kind(&self) -> ProposalKind {
+        match *self {
+            ProposalCategory::Signaling => ProposalKind::Signaling,
+            ProposalCategory::Funding(..) => ProposalKind::Funding,
+            ProposalCategory::Upgrade(..) => ProposalKind::Upgrade,
+            ProposalCategory::Call(..) => ProposalKind::Call,
+        }
+    }
+}
+
+/// The support a proposal of a given kind needs in order to pass.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy)]
+pub struct CategoryRules {
+    /// Share of the total issuance outside the treasury that must vote for
+    /// the result to count
+    pub min_turnout: Permill,
+    /// Share of the stake voting that must vote yes
+    pub approval: Permill,
+    /// How turnout biases the support needed to pass
+    pub threshold: VoteThreshold,
+}
+
+impl CategoryRules {
+    /// The rules for a kind of proposal that has none configured. Upgrade
+    /// and Call proposals act with root privileges and Funding proposals
+    /// spend the treasury, so they need half the electorate to turn out and
+    /// more than two-thirds of the votes; Signaling proposals need no
+    /// minimum turnout and a simple majority.
+    pub fn fallback(kind: ProposalKind) -> Self {
+        match kind {
+            ProposalKind::Upgrade | ProposalKind::Call | ProposalKind::Funding => CategoryRules {
+                min_turnout: Permill::from_percent(50),
+                // just over two-thirds, so that a vote still needs more than
+                // two-thirds after the share is rounded down
+                approval: Permill::from_millionths(666_667),
+                threshold: VoteThreshold::SuperMajorityApprove,
+            },
+            ProposalKind::Signaling => CategoryRules {
+                min_turnout: Permill::from_percent(0),
+                approval: Permill::from_percent(50),
+                threshold: VoteThreshold::SimpleMajority,
+            },
+        }
+    }
+}
+
+/// How strongly a voter backs their vote. Each step multiplies the weight of
+/// the vote and lengthens the time the voter's balance stays locked after the
+/// proposal completes.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy)]
+pub enum Conviction {
+    /// 1x weight, no lock
+    None,
+    Locked2x,
+    Locked3x,
+    Locked4x,
+    Locked5x,
+    Locked6x,
+}
+
+impl Default for Conviction {
+    fn default() -> Self {
+        Conviction::None
+    }
+}
+
+impl Conviction {
+    pub fn multiplier(self) -> u64 {
+        match self {
+            Conviction::None => 1,
+            Conviction::Locked2x => 2,
+            Conviction::Locked3x => 3,
+            Conviction::Locked4x => 4,
+            Conviction::Locked5x => 5,
+            Conviction::Locked6x => 6,
+        }
+    }
+
+    /// The number of lock periods the voter's balance is locked for.
+    pub fn lock_periods(self) -> u64 {
+        match self {
+            Conviction::None => 0,
+            _ => self.multiplier(),
+        }
+    }
+}
+
+/// What a conviction lock holds an account's balance for.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy)]
+pub enum LockReason<Hash> {
+    /// A conviction vote on the proposal with this hash
+    Vote(Hash),
+    /// A conviction delegation for a kind of proposal, or by default for `None`
+    Delegation(Option<ProposalKind>),
+}
+
+/// The choice index of a yes vote on a proposal without explicit choices.
+pub const AYE: u32 = 0;
+/// The choice index of a no vote on a proposal without explicit choices.
+pub const NAY: u32 = 1;
+
+/// How the votes on a proposal are counted.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy)]
+pub enum VotingScheme {
+    /// Each vote backs a single choice
+    SingleChoice,
+    /// Each vote backs any number of choices with its full weight
+    Approval,
+    /// Each vote ranks choices in order of preference, and choices are
+    /// eliminated one round at a time until one has a majority
+    RankedChoice,
+    /// Each vote backs a single choice with credits reserved from the
+    /// voter's balance, and counts with the square root of its credits
+    Quadratic,
+}
+
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, PartialEq, Eq, Clone)]
+pub struct Vote {
+    /// Indices into the proposal's choices, or `AYE`/`NAY` on a yes/no
+    /// ballot. Holds exactly one choice under `SingleChoice`, and in order of
+    /// preference under `RankedChoice`.
+    pub choices: Vec<u32>,
+    pub conviction: Conviction,
+}
+
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy)]
+pub enum ProposalOutcome {
+    Passed,
+    Rejected,
+    NoQuorum,
 }
 
 #[cfg_attr(feature = "std", derive(Debug))]
 #[derive(Encode, Decode, PartialEq)]
-pub struct ProposalRecord<AccountId> {
+pub struct ProposalRecord<AccountId, Balance, BlockNumber, Hash> {
     pub index: u32,
     pub author: AccountId,
     pub stage: ProposalStage,
-    pub category: ProposalCategory,
+    pub category: ProposalCategory<AccountId, Balance, Hash>,
+    pub title: Vec<u8>,
+    pub contents: ProposalContents<Hash>,
+    /// The number of the latest revision of the title and contents
+    pub revision: u32,
+    /// The options on the ballot; empty for a yes/no ballot
+    pub choices: Vec<Vec<u8>>,
+    pub scheme: VotingScheme,
+    /// Whether votes are committed as hashes while voting, then revealed
+    pub secret_ballot: bool,
+    pub deposit: Balance,
+    pub voting_starts_at: BlockNumber,
+    pub voting_ends_at: BlockNumber,
+    pub outcome: Option<ProposalOutcome>,
+    pub winning_choice: Option<u32>,
+}
+
+impl<AccountId, Balance, BlockNumber, Hash> ProposalRecord<AccountId, Balance, BlockNumber, Hash> {
+    /// The number of options a vote on this proposal can choose between.
+    pub fn choice_count(&self) -> u32 {
+        if self.choices.is_empty() { 2 } else { self.choices.len() as u32 }
+    }
+}
+
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, PartialEq)]
+pub struct CommentRecord<AccountId, BlockNumber> {
+    pub author: AccountId,
+    /// The comment this is a reply to, if any
+    pub parent: Option<u32>,
+    pub body: Vec<u8>,
+    pub posted_at: BlockNumber,
+    pub edit_count: u32,
+    pub last_edited_at: Option<BlockNumber>,
+    /// Deleted comments keep their place in the thread but lose their body
+    pub deleted: bool,
+}
+
+/// A version of a proposal's title and contents.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, PartialEq)]
+pub struct ProposalRevision<BlockNumber, Hash> {
+    pub revision: u32,
+    pub revised_at: BlockNumber,
     pub title: Vec<u8>,
-    pub contents: Vec<u8>,
-    // TODO: separate comments into different object, for storage reasons
-    pub comments: Vec<(Vec<u8>, AccountId)>,
+    pub contents: ProposalContents<Hash>,
+}
+
+pub type ProposalRevisionOf<T> = ProposalRevision<<T as system::Trait>::BlockNumber,
+                                                  <T as system::Trait>::Hash>;
+
+pub type CommentRecordOf<T> = CommentRecord<<T as system::Trait>::AccountId,
+                                            <T as system::Trait>::BlockNumber>;
+
+pub type ProposalContentsOf<T> = ProposalContents<<T as system::Trait>::Hash>;
+
+pub type ProposalCategoryOf<T> = ProposalCategory<<T as system::Trait>::AccountId,
+                                                  <T as balances::Trait>::Balance,
+                                                  <T as system::Trait>::Hash>;
+
+pub type ProposalRecordOf<T> = ProposalRecord<<T as system::Trait>::AccountId,
+                                              <T as balances::Trait>::Balance,
+                                              <T as system::Trait>::BlockNumber,
+                                              <T as system::Trait>::Hash>;
+
+/// A conviction lock on an account: what it is held for, the balance it
+/// holds, and the block it expires at, or `None` while its delegation stands.
+pub type LockOf<T> = (LockReason<<T as system::Trait>::Hash>,
+                      <T as balances::Trait>::Balance,
+                      Option<<T as system::Trait>::BlockNumber>);
+
+/// An account counted towards a proposal's votes, with the choices it backs,
+/// the stake it is counted with and its conviction.
+type BallotOf<T> = (<T as system::Trait>::AccountId, Vec<u32>, <T as balances::Trait>::Balance, Conviction);
+
+/// Handler for proposal deposits that have been slashed.
+pub trait OnDepositSlashed<Balance> {
+    /// Called with the amount slashed, which has already been removed from
+    /// the author's reserved balance.
+    fn on_deposit_slashed(amount: Balance);
+}
+
+impl<Balance> OnDepositSlashed<Balance> for () {
+    fn on_deposit_slashed(_amount: Balance) {}
+}
+
+/// Check that a call comes from an origin with some privilege, such as a
+/// council or technical committee.
+pub trait EnsureOrigin<OuterOrigin> {
+    fn ensure_origin(origin: OuterOrigin) -> Result;
 }
 
-pub trait Trait: system::Trait {
+/// Accepts only the root origin.
+pub struct EnsureRoot<AccountId>(PhantomData<AccountId>);
+
+impl<OuterOrigin, AccountId> EnsureOrigin<OuterOrigin> for EnsureRoot<AccountId>
+    where OuterOrigin: Into<Option<system::RawOrigin<AccountId>>>
+{
+    fn ensure_origin(origin: OuterOrigin) -> Result {
+        ensure_root(origin)
+    }
+}
+
+/// Proposals are weighed by the stake behind their votes, and deposits,
+/// vote credits and Funding payouts are all held in the runtime's balances,
+/// so the module cannot run without `balances`.
+pub trait Trait: balances::Trait {
     /// The overarching event type
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+
+    /// Number of blocks a new proposal spends in PreVoting before voting opens
+    const PRE_VOTING_PERIOD: Self::BlockNumber;
+
+    /// Number of blocks a new proposal spends in PreVoting, open to edits and
+    /// comments, before its author may open voting early
+    const MIN_PRE_VOTING_PERIOD: Self::BlockNumber;
+
+    /// Number of blocks a proposal stays open for voting
+    const VOTING_PERIOD: Self::BlockNumber;
+
+    /// Number of blocks voters on a secret ballot have to reveal their votes
+    const REVEAL_PERIOD: Self::BlockNumber;
+
+    /// Number of blocks in one conviction lock period
+    const LOCK_PERIOD: Self::BlockNumber;
+
+    /// Number of blocks a fast-tracked proposal stays open for voting
+    const FAST_TRACK_VOTING_PERIOD: Self::BlockNumber;
+
+    /// Number of blocks after a veto during which the vetoed contents cannot
+    /// be proposed again
+    const VETO_COOLOFF_PERIOD: Self::BlockNumber;
+
+    /// Where the deposits of cancelled proposals are sent
+    type DepositSink: OnDepositSlashed<Self::Balance>;
+
+    /// The origin allowed to fast-track Upgrade proposals into voting
+    type FastTrackOrigin: EnsureOrigin<Self::Origin>;
+
+    /// The origin allowed to veto proposals
+    type VetoOrigin: EnsureOrigin<Self::Origin>;
+
+    /// The origin allowed to cancel abusive proposals
+    type CancelOrigin: EnsureOrigin<Self::Origin>;
+
+    /// The outer call type that Call proposals are decoded into
+    type Proposal: Parameter + Dispatchable<Origin=Self::Origin>;
 }
 
 decl_module! {
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
         fn deposit_event() = default;
 
-        pub fn create_proposal(origin, title: Vec<u8>, contents: Vec<u8>, category: ProposalCategory) -> Result {
+        fn on_finalise(n: T::BlockNumber) {
+            if let Err(e) = Self::end_block(n) {
+                runtime_io::print(e);
+            }
+        }
+
+        /// Submit a new proposal, whose `contents` are stored inline, noted
+        /// with `note_preimage` or stored off-chain. Signaling proposals may list their own
+        /// `choices` and count them under any voting scheme; any other
+        /// proposal is put to a single choice yes/no vote. Votes on a
+        /// `secret_ballot` are committed as hashes and revealed once voting ends.
+        pub fn create_proposal(origin, title: Vec<u8>, contents: ProposalContentsOf<T>, category: ProposalCategoryOf<T>, choices: Vec<Vec<u8>>, scheme: VotingScheme, secret_ballot: bool) -> Result {
             let _sender = ensure_signed(origin)?;
             ensure!(!title.is_empty(), "Proposal must have title");
-            ensure!(!contents.is_empty(), "Proposal must not be empty");
+            Self::check_contents(&contents)?;
+            if !choices.is_empty() {
+                ensure!(category.kind() == ProposalKind::Signaling, "Only signaling proposals may have choices");
+                ensure!(choices.len() >= 2, "Proposal must offer at least two choices");
+                ensure!(choices.iter().all(|choice| !choice.is_empty()), "Choices must not be empty");
+            } else {
+                ensure!(scheme == VotingScheme::SingleChoice || scheme == VotingScheme::Quadratic,
+                        "Only proposals with choices may use this voting scheme");
+            }
+            if scheme == VotingScheme::Quadratic {
+                ensure!(category.kind() == ProposalKind::Signaling, "Only signaling proposals may vote quadratically");
+                ensure!(!secret_ballot, "Quadratic votes cannot be secret");
+            }
+            match category {
+                ProposalCategory::Funding(amount, _) => {
+                    ensure!(!amount.is_zero(), "Funding amount must not be zero");
+                },
+                ProposalCategory::Upgrade(code_hash) => {
+                    ensure!(<PreimageOf<T>>::exists(&code_hash), "Upgrade code has not been noted");
+                },
+                ProposalCategory::Call(ref encoded) => {
+                    ensure!(T::Proposal::decode(&mut &encoded[..]).is_some(), "Call could not be decoded");
+                },
+                ProposalCategory::Signaling => (),
+            }
+
+            let now = <system::Module<T>>::block_number();
+            ensure!(Self::vetoed_until(Self::contents_hash(&contents)).map_or(true, |until| until <= now),
+                    "Proposal is cooling off after a veto");
 
-            // construct hash(origin + proposal) and check existence
-            // TODO: include title/category/etc?
+            let deposit = Self::proposal_deposit();
+            <balances::Module<T>>::reserve(&_sender, deposit).map_err(|_| "Proposer's balance too low")?;
+
+            // construct hash(origin + proposal + index), which the index
+            // keeps unique even when the same contents are proposed again
+            let index = <ProposalCount<T>>::get();
+            <ProposalCount<T>>::mutate(|i| *i += 1);
             let mut buf = Vec::new();
             buf.extend_from_slice(&_sender.encode());
-            buf.extend_from_slice(&contents.as_ref());
+            buf.extend_from_slice(&contents.encode());
+            buf.extend_from_slice(&index.encode());
             let hash = T::Hashing::hash(&buf[..]);
-            ensure!(<ProposalOf<T>>::get(&hash) == None, "Proposal already exists");
 
             // construct proposal
-            let index = <ProposalCount<T>>::get();
-            <ProposalCount<T>>::mutate(|i| *i += 1);
+            let voting_starts_at = now + T::PRE_VOTING_PERIOD;
+            let voting_ends_at = voting_starts_at + T::VOTING_PERIOD;
             let record = ProposalRecord { index: index,
                                           author: _sender.clone(),
                                           stage: ProposalStage::PreVoting,
                                           category: category,
-                                          title: title,
-                                          contents: contents,
-                                          comments: vec![] };
+                                          title: title.clone(),
+                                          contents: contents.clone(),
+                                          revision: 0,
+                                          choices: choices,
+                                          scheme: scheme,
+                                          secret_ballot: secret_ballot,
+                                          deposit: deposit,
+                                          voting_starts_at: voting_starts_at,
+                                          voting_ends_at: voting_ends_at,
+                                          outcome: None,
+                                          winning_choice: None };
 
             // add new record to storage
+            Self::use_preimages(&record);
             <ProposalOf<T>>::insert(&hash, record);
+            <ProposalHashOf<T>>::insert(index, hash);
+            <RevisionOf<T>>::insert((hash, 0), ProposalRevision { revision: 0,
+                                                                 revised_at: now,
+                                                                 title: title,
+                                                                 contents: contents });
             let mut proposals = Self::proposals();
             proposals.push(hash.clone());
             <Proposals<T>>::put(proposals);
-            Self::deposit_event(RawEvent::NewProposal(_sender, hash));
+            <ActiveProposals<T>>::mutate(|active| active.push((hash, voting_starts_at)));
+            Self::deposit_event(RawEvent::NewProposal(_sender.clone(), index, hash));
+            Self::deposit_event(RawEvent::DepositReserved(hash, _sender, deposit));
             Ok(())
         }
 
-        // TODO: give comments unique numbers/ids?
-        pub fn add_comment(origin, proposal_hash: T::Hash, comment: Vec<u8>) -> Result {
+        /// Replace the title and contents of a proposal before voting opens,
+        /// keeping every earlier revision. The proposal keeps its hash.
+        pub fn edit_proposal(origin, proposal_index: u32, title: Vec<u8>, contents: ProposalContentsOf<T>) -> Result {
             let _sender = ensure_signed(origin)?;
-            // TODO: can we mut borrow somehow?
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
             let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
+            ensure!(record.author == _sender, "Proposal must be edited by author");
+            ensure!(record.stage == ProposalStage::PreVoting, "Proposal can only be edited before voting");
+            ensure!(!title.is_empty(), "Proposal must have title");
+            Self::check_contents(&contents)?;
+            let now = <system::Module<T>>::block_number();
+            ensure!(Self::vetoed_until(Self::contents_hash(&contents)).map_or(true, |until| until <= now),
+                    "Proposal is cooling off after a veto");
+
+            let revision = record.revision + 1;
+            Self::release_preimages(&record);
             let mut new_record = record;
-            new_record.comments.push((comment, _sender.clone()));
+            new_record.title = title.clone();
+            new_record.contents = contents.clone();
+            new_record.revision = revision;
+            Self::use_preimages(&new_record);
             <ProposalOf<T>>::insert(&proposal_hash, new_record);
-            Self::deposit_event(RawEvent::NewComment(_sender, proposal_hash));
+            <RevisionOf<T>>::insert((proposal_hash, revision), ProposalRevision { revision: revision,
+                                                                                revised_at: now,
+                                                                                title: title,
+                                                                                contents: contents });
+            Self::deposit_event(RawEvent::ProposalEdited(proposal_hash, revision));
+            Ok(())
+        }
+
+        /// Change the turnout and approval a kind of proposal needs to pass.
+        /// Applies to every proposal of that kind that has not yet completed.
+        pub fn set_category_rules(origin, kind: ProposalKind, rules: CategoryRules) -> Result {
+            ensure_root(origin)?;
+            <CategoryRulesOf<T>>::insert(kind, rules);
+            Ok(())
+        }
+
+        pub fn add_comment(origin, proposal_index: u32, comment: Vec<u8>, parent: Option<u32>) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            if let Some(parent_id) = parent {
+                ensure!(<CommentOf<T>>::exists((proposal_hash, parent_id)), "Parent comment does not exist");
+            }
+
+            // comments are numbered sequentially per proposal
+            let comment_id = Self::comment_count(proposal_hash);
+            <CommentCount<T>>::insert(proposal_hash, comment_id + 1);
+            let record = CommentRecord { author: _sender.clone(),
+                                         parent: parent,
+                                         body: comment,
+                                         posted_at: <system::Module<T>>::block_number(),
+                                         edit_count: 0,
+                                         last_edited_at: None,
+                                         deleted: false };
+            <CommentOf<T>>::insert((proposal_hash, comment_id), record);
+            Self::deposit_event(RawEvent::NewComment(_sender, proposal_hash, comment_id));
+            Ok(())
+        }
+
+        pub fn edit_comment(origin, proposal_index: u32, comment_id: u32, comment: Vec<u8>) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            let record = Self::comment_of((proposal_hash, comment_id)).ok_or("Comment does not exist")?;
+            ensure!(record.author == _sender, "Comment must be edited by author");
+            ensure!(!record.deleted, "Comment was deleted");
+
+            let mut new_record = record;
+            new_record.body = comment;
+            new_record.edit_count += 1;
+            new_record.last_edited_at = Some(<system::Module<T>>::block_number());
+            <CommentOf<T>>::insert((proposal_hash, comment_id), new_record);
+            Self::deposit_event(RawEvent::CommentEdited(_sender, proposal_hash, comment_id));
+            Ok(())
+        }
+
+        pub fn delete_comment(origin, proposal_index: u32, comment_id: u32) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            let record = Self::comment_of((proposal_hash, comment_id)).ok_or("Comment does not exist")?;
+            ensure!(record.author == _sender, "Comment must be deleted by author");
+            ensure!(!record.deleted, "Comment was deleted");
+
+            // replies keep pointing at the deleted comment, so only its body is dropped
+            let mut new_record = record;
+            new_record.body = vec![];
+            new_record.deleted = true;
+            <CommentOf<T>>::insert((proposal_hash, comment_id), new_record);
+            Self::deposit_event(RawEvent::CommentDeleted(_sender, proposal_hash, comment_id));
+            Ok(())
+        }
+
+        /// Store a preimage, such as a proposal's contents or new runtime
+        /// code for an Upgrade proposal, so that proposals can refer to it by
+        /// hash. Reserves a deposit for each byte stored.
+        pub fn note_preimage(origin, preimage: Vec<u8>) -> Result {
+            let _sender = ensure_signed(origin)?;
+            ensure!(!preimage.is_empty(), "Preimage must not be empty");
+            ensure!(preimage.len() <= MAX_PREIMAGE_LEN, "Preimage is too long");
+            let hash = T::Hashing::hash(&preimage[..]);
+            ensure!(!<PreimageOf<T>>::exists(&hash), "Preimage already noted");
+
+            let deposit = Self::preimage_byte_deposit() * T::Balance::sa(preimage.len() as u64);
+            <balances::Module<T>>::reserve(&_sender, deposit).map_err(|_| "Balance too low to note preimage")?;
+            <PreimageOf<T>>::insert(&hash, (preimage, _sender.clone(), deposit));
+            Self::deposit_event(RawEvent::PreimageNoted(hash, _sender));
             Ok(())
         }
 
-        pub fn advance_proposal(origin, proposal_hash: T::Hash) -> Result {
+        /// Remove a preimage that no open proposal refers to, returning its
+        /// deposit to the account that noted it.
+        pub fn reap_preimage(origin, hash: T::Hash) -> Result {
             let _sender = ensure_signed(origin)?;
+            let (_, who, deposit) = Self::preimage_of(&hash).ok_or("Preimage does not exist")?;
+            ensure!(who == _sender, "Preimage must be reaped by the account that noted it");
+            ensure!(Self::preimage_users(&hash) == 0, "Preimage is still in use");
+
+            <balances::Module<T>>::unreserve(&who, deposit);
+            <PreimageOf<T>>::remove(&hash);
+            Self::deposit_event(RawEvent::PreimageReaped(hash, who));
+            Ok(())
+        }
+
+        /// Open voting on a proposal before its pre-voting period has elapsed,
+        /// once at least the minimum pre-voting period has. Voting always
+        /// closes automatically at the end of the voting period.
+        pub fn advance_proposal(origin, proposal_index: u32) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
             let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
 
             // only permit original author to advance
             ensure!(record.author == _sender, "Proposal must be advanced by author");
-            let next_stage = match record.stage {
-                ProposalStage::PreVoting => ProposalStage::Voting,
-                ProposalStage::Voting    => ProposalStage::Completed,
+            match record.stage {
+                ProposalStage::PreVoting => (),
+                ProposalStage::Voting | ProposalStage::Revealing => { return Err("Voting period has not ended") },
+                ProposalStage::Completed => { return Err("Proposal already completed") },
+                ProposalStage::Cancelled => { return Err("Proposal was cancelled") },
+                ProposalStage::Withdrawn => { return Err("Proposal was withdrawn") },
+                ProposalStage::Vetoed => { return Err("Proposal was vetoed") },
+            };
+            // a proposal in pre-voting is due to open voting a full
+            // pre-voting period after it was created
+            let now = <system::Module<T>>::block_number();
+            ensure!(now + T::PRE_VOTING_PERIOD >= record.voting_starts_at + T::MIN_PRE_VOTING_PERIOD,
+                    "Minimum pre-voting period has not passed");
+            <ActiveProposals<T>>::mutate(|active| active.retain(|&(hash, _)| hash != proposal_hash));
+            Self::start_voting(proposal_hash, record, now, T::VOTING_PERIOD);
+            Ok(())
+        }
+
+        /// Open voting on an Upgrade proposal straight away, for the shorter
+        /// fast-track voting period.
+        pub fn fast_track(origin, proposal_index: u32) -> Result {
+            T::FastTrackOrigin::ensure_origin(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
+            ensure!(record.category.kind() == ProposalKind::Upgrade, "Only upgrade proposals may be fast-tracked");
+            ensure!(record.stage == ProposalStage::PreVoting, "Proposal not in pre-voting stage");
+            <ActiveProposals<T>>::mutate(|active| active.retain(|&(hash, _)| hash != proposal_hash));
+            Self::start_voting(proposal_hash, record, <system::Module<T>>::block_number(), T::FAST_TRACK_VOTING_PERIOD);
+            Self::deposit_event(RawEvent::ProposalFastTracked(proposal_hash));
+            Ok(())
+        }
+
+        /// Stop a proposal that has not yet completed, returning its author's
+        /// deposit. Its contents cannot be proposed again until the cool-off
+        /// period has passed.
+        pub fn veto(origin, proposal_index: u32) -> Result {
+            T::VetoOrigin::ensure_origin(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
+            Self::ensure_open(&record)?;
+            Self::close_proposal(proposal_hash, &record);
+            <balances::Module<T>>::unreserve(&record.author, record.deposit);
+
+            let until = <system::Module<T>>::block_number() + T::VETO_COOLOFF_PERIOD;
+            <VetoedUntil<T>>::insert(Self::contents_hash(&record.contents), until);
+            let (author, deposit) = (record.author.clone(), record.deposit);
+            let mut new_record = record;
+            new_record.stage = ProposalStage::Vetoed;
+            <ProposalOf<T>>::insert(&proposal_hash, new_record);
+            Self::deposit_event(RawEvent::ProposalVetoed(proposal_hash, until));
+            Self::deposit_event(RawEvent::DepositReturned(proposal_hash, author, deposit));
+            Ok(())
+        }
+
+        /// Take back a proposal before voting opens, returning its author's
+        /// deposit.
+        pub fn withdraw_proposal(origin, proposal_index: u32) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
+            ensure!(record.author == _sender, "Proposal must be withdrawn by author");
+            match record.stage {
+                ProposalStage::PreVoting => (),
+                ProposalStage::Voting | ProposalStage::Revealing => { return Err("Proposal can only be withdrawn before voting") },
                 ProposalStage::Completed => { return Err("Proposal already completed") },
+                ProposalStage::Cancelled => { return Err("Proposal was cancelled") },
+                ProposalStage::Withdrawn => { return Err("Proposal was withdrawn") },
+                ProposalStage::Vetoed => { return Err("Proposal was vetoed") },
             };
+            <ActiveProposals<T>>::mutate(|active| active.retain(|&(hash, _)| hash != proposal_hash));
+            <balances::Module<T>>::unreserve(&record.author, record.deposit);
+            Self::release_preimages(&record);
+
+            let deposit = record.deposit;
             let mut new_record = record;
-            new_record.stage = next_stage;
+            new_record.stage = ProposalStage::Withdrawn;
             <ProposalOf<T>>::insert(&proposal_hash, new_record);
-            if next_stage == ProposalStage::Voting {
-                Self::deposit_event(RawEvent::VotingStarted(proposal_hash));
-            } else if next_stage == ProposalStage::Completed {
-                Self::deposit_event(RawEvent::VotingCompleted(proposal_hash));
+            Self::deposit_event(RawEvent::ProposalWithdrawn(proposal_hash));
+            Self::deposit_event(RawEvent::DepositReturned(proposal_hash, _sender, deposit));
+            Ok(())
+        }
+
+        /// Cancel an abusive proposal that has not yet completed, slashing
+        /// its author's deposit.
+        pub fn cancel_proposal(origin, proposal_index: u32) -> Result {
+            T::CancelOrigin::ensure_origin(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
+            Self::ensure_open(&record)?;
+            Self::close_proposal(proposal_hash, &record);
+
+            let remaining = <balances::Module<T>>::slash_reserved(&record.author, record.deposit);
+            let slashed = record.deposit - remaining.unwrap_or(Zero::zero());
+            T::DepositSink::on_deposit_slashed(slashed);
+
+            let author = record.author.clone();
+            let mut new_record = record;
+            new_record.stage = ProposalStage::Cancelled;
+            <ProposalOf<T>>::insert(&proposal_hash, new_record);
+            Self::deposit_event(RawEvent::ProposalCancelled(proposal_hash));
+            Self::deposit_event(RawEvent::DepositSlashed(proposal_hash, author, slashed));
+            Ok(())
+        }
+
+        /// Vote on a proposal. A vote with a conviction locks `balance` from
+        /// the voter's free balance and counts with it, while a vote without
+        /// one locks nothing and counts with the voter's stake.
+        pub fn submit_vote(origin, proposal_index: u32, vote: Vote, balance: T::Balance) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
+            ensure!(record.stage == ProposalStage::Voting, "Proposal not in voting stage");
+            ensure!(Self::vote_of((proposal_hash, _sender.clone())).is_none(), "Vote already submitted");
+            ensure!(record.scheme != VotingScheme::Quadratic, "Quadratic votes must commit credits");
+            ensure!(!record.secret_ballot, "Votes on a secret ballot must be committed");
+            Self::check_vote(&record, &vote)?;
+            Self::check_lock(vote.conviction, balance)?;
+            Self::lock_vote(&_sender, proposal_hash, &record, vote.conviction, balance)?;
+
+            <ProposalVoters<T>>::mutate(proposal_hash, |voters| voters.push(_sender.clone()));
+            <VoteOf<T>>::insert((proposal_hash.clone(), _sender.clone()), vote.clone());
+            Self::deposit_event(RawEvent::VoteSubmitted(proposal_hash, _sender, vote));
+            Ok(())
+        }
+
+        /// Vote on a quadratic proposal by reserving `credits` from the
+        /// voter's balance. The vote counts with the square root of its
+        /// credits, which are returned once voting completes, and cannot
+        /// carry a conviction.
+        pub fn submit_quadratic_vote(origin, proposal_index: u32, vote: Vote, credits: T::Balance) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
+            ensure!(record.stage == ProposalStage::Voting, "Proposal not in voting stage");
+            ensure!(Self::vote_of((proposal_hash, _sender.clone())).is_none(), "Vote already submitted");
+            ensure!(record.scheme == VotingScheme::Quadratic, "Proposal does not use quadratic voting");
+            ensure!(!credits.is_zero(), "Vote must commit credits");
+            Self::check_vote(&record, &vote)?;
+
+            <balances::Module<T>>::reserve(&_sender, credits)
+                .map_err(|_| "Voter's balance too low")?;
+            <ProposalVoters<T>>::mutate(proposal_hash, |voters| voters.push(_sender.clone()));
+            <CreditsOf<T>>::insert((proposal_hash, _sender.clone()), credits);
+            <VoteOf<T>>::insert((proposal_hash.clone(), _sender.clone()), vote.clone());
+            Self::deposit_event(RawEvent::VoteSubmitted(proposal_hash, _sender, vote));
+            Ok(())
+        }
+
+        /// Replace a previously submitted vote, along with the balance locked
+        /// behind it. Quadratic votes keep the credits already committed.
+        pub fn change_vote(origin, proposal_index: u32, vote: Vote, balance: T::Balance) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
+            ensure!(record.stage == ProposalStage::Voting, "Proposal not in voting stage");
+            ensure!(Self::vote_of((proposal_hash, _sender.clone())).is_some(), "Vote has not been submitted");
+            Self::check_vote(&record, &vote)?;
+            Self::check_lock(vote.conviction, balance)?;
+            Self::lock_vote(&_sender, proposal_hash, &record, vote.conviction, balance)?;
+
+            <VoteOf<T>>::insert((proposal_hash.clone(), _sender.clone()), vote.clone());
+            Self::deposit_event(RawEvent::VoteChanged(proposal_hash, _sender, vote));
+            Ok(())
+        }
+
+        /// Withdraw a previously submitted vote so that it is not counted,
+        /// releasing any balance locked behind it.
+        pub fn retract_vote(origin, proposal_index: u32) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
+            ensure!(record.stage == ProposalStage::Voting, "Proposal not in voting stage");
+            ensure!(Self::vote_of((proposal_hash, _sender.clone())).is_some(), "Vote has not been submitted");
+
+            if record.scheme == VotingScheme::Quadratic {
+                <balances::Module<T>>::unreserve(&_sender, Self::credits_of((proposal_hash, _sender.clone())));
+                <CreditsOf<T>>::remove((proposal_hash, _sender.clone()));
             }
+            Self::release_vote_lock(&_sender, proposal_hash);
+            <VoteOf<T>>::remove((proposal_hash.clone(), _sender.clone()));
+            <ProposalVoters<T>>::mutate(proposal_hash, |voters| voters.retain(|voter| *voter != _sender));
+            Self::deposit_event(RawEvent::VoteRetracted(proposal_hash, _sender));
             Ok(())
         }
 
-        pub fn submit_vote(origin, proposal_hash: T::Hash, vote: bool) -> Result {
+        /// Commit to a vote on a secret ballot with the hash of the voter's
+        /// account, the vote and a secret salt, reserving the commitment
+        /// deposit. A commitment may be
+        /// replaced until voting ends.
+        pub fn commit_vote(origin, proposal_index: u32, commitment: T::Hash) -> Result {
             let _sender = ensure_signed(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
             let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
             ensure!(record.stage == ProposalStage::Voting, "Proposal not in voting stage");
+            ensure!(record.secret_ballot, "Proposal does not use a secret ballot");
 
-            // TODO: This does not allow updating one's vote; should we support this?
-            if Self::vote_of((proposal_hash, _sender.clone())).is_none() {
-                <ProposalVoters<T>>::mutate(proposal_hash, |voters| voters.push(_sender.clone()));
+            let deposit = match Self::commitment_of((proposal_hash, _sender.clone())) {
+                Some((_, deposit)) => deposit,
+                None => {
+                    let deposit = Self::commitment_deposit();
+                    <balances::Module<T>>::reserve(&_sender, deposit).map_err(|_| "Voter's balance too low")?;
+                    <ProposalCommitters<T>>::mutate(proposal_hash, |committers| committers.push(_sender.clone()));
+                    deposit
+                },
+            };
+            <CommitmentOf<T>>::insert((proposal_hash, _sender.clone()), (commitment, deposit));
+            Self::deposit_event(RawEvent::VoteCommitted(proposal_hash, _sender));
+            Ok(())
+        }
+
+        /// Reveal a vote committed to a secret ballot, returning the
+        /// commitment deposit. The balance locked behind a conviction vote is
+        /// only chosen on reveal.
+        pub fn reveal_vote(origin, proposal_index: u32, vote: Vote, salt: Vec<u8>, balance: T::Balance) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let proposal_hash = Self::proposal_hash_of(proposal_index).ok_or("Proposal does not exist")?;
+            let record = <ProposalOf<T>>::get(&proposal_hash).ok_or("Proposal does not exist")?;
+            ensure!(record.stage == ProposalStage::Revealing, "Proposal not in reveal stage");
+            let (commitment, deposit) = Self::commitment_of((proposal_hash, _sender.clone())).ok_or("Vote has not been committed")?;
+            ensure!(Self::vote_of((proposal_hash, _sender.clone())).is_none(), "Vote already revealed");
+            // the voter's account is part of the commitment, so that a copy of
+            // another voter's commitment cannot be revealed with their vote
+            ensure!(T::Hashing::hash_of(&(_sender.clone(), vote.clone(), salt)) == commitment, "Vote does not match commitment");
+            Self::check_vote(&record, &vote)?;
+            Self::check_lock(vote.conviction, balance)?;
+            Self::lock_vote(&_sender, proposal_hash, &record, vote.conviction, balance)?;
+
+            <balances::Module<T>>::unreserve(&_sender, deposit);
+            <ProposalVoters<T>>::mutate(proposal_hash, |voters| voters.push(_sender.clone()));
+            <VoteOf<T>>::insert((proposal_hash.clone(), _sender.clone()), vote.clone());
+            Self::deposit_event(RawEvent::VoteRevealed(proposal_hash, _sender, vote));
+            Ok(())
+        }
+
+        /// Delegate the sender's votes to another account, which counts the
+        /// sender's stake with its own vote on any proposal the sender does
+        /// not vote on directly. A delegation with a conviction locks `balance`
+        /// for as long as it stands, and for as many lock periods as its
+        /// conviction asks for after it is replaced or removed, and counts
+        /// with that balance multiplied by the conviction.
+        /// A delegation for a `kind` of proposal takes precedence over the
+        /// default delegation, made with a `kind` of `None`.
+        pub fn delegate(origin, to: T::AccountId, conviction: Conviction, balance: T::Balance, kind: Option<ProposalKind>) -> Result {
+            let _sender = ensure_signed(origin)?;
+            ensure!(to != _sender, "Cannot delegate to self");
+            // the new delegation must not lead back to the sender for any
+            // kind of proposal that it applies to
+            let affected: Vec<ProposalKind> = match kind {
+                Some(kind) => vec![kind],
+                // a default delegation only applies to kinds without their own delegation
+                None => ProposalKind::ALL.iter().cloned()
+                    .filter(|k| Self::delegation_of((_sender.clone(), Some(*k))).is_none())
+                    .collect(),
+            };
+            for k in affected {
+                ensure!(!Self::delegates_to(&to, &_sender, k), "Delegation would create a cycle");
             }
-            <VoteOf<T>>::insert((proposal_hash.clone(), _sender.clone()), vote);
-            Self::deposit_event(RawEvent::VoteSubmitted(proposal_hash, _sender, vote));
+            Self::check_lock(conviction, balance)?;
+
+            let previous = Self::delegation_of((_sender.clone(), kind));
+            let mut locks = Self::locks_of(&_sender);
+            if let Some((_, previous_conviction, _)) = previous {
+                Self::release_delegation_lock(&mut locks, kind, previous_conviction);
+            }
+            if conviction != Conviction::None {
+                locks.push((LockReason::Delegation(kind), balance, None));
+            }
+            Self::set_locks(&_sender, locks)?;
+
+            if let Some((previous, _, _)) = previous {
+                <DelegatorsOf<T>>::mutate((previous, kind), |delegators| delegators.retain(|delegator| *delegator != _sender));
+            }
+            <DelegatorsOf<T>>::mutate((to.clone(), kind), |delegators| delegators.push(_sender.clone()));
+            <DelegationOf<T>>::insert((_sender.clone(), kind), (to.clone(), conviction, balance));
+            Self::deposit_event(RawEvent::Delegated(_sender, to, kind));
+            Ok(())
+        }
+
+        /// Stop delegating the sender's votes for a `kind` of proposal, or
+        /// by default. Removing a delegation for a kind of proposal falls
+        /// back to the default delegation. The balance locked behind the
+        /// delegation stays locked for as many lock periods as its conviction
+        /// asks for.
+        pub fn undelegate(origin, kind: Option<ProposalKind>) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let (delegate, conviction, _) = Self::delegation_of((_sender.clone(), kind)).ok_or("Account is not delegating")?;
+            if let (Some(kind), Some((default, _, _))) = (kind, Self::delegation_of((_sender.clone(), None))) {
+                ensure!(!Self::delegates_to(&default, &_sender, kind), "Delegation would create a cycle");
+            }
+            let mut locks = Self::locks_of(&_sender);
+            Self::release_delegation_lock(&mut locks, kind, conviction);
+            Self::set_locks(&_sender, locks)?;
+            <DelegatorsOf<T>>::mutate((delegate, kind), |delegators| delegators.retain(|delegator| *delegator != _sender));
+            <DelegationOf<T>>::remove((_sender.clone(), kind));
+            Self::deposit_event(RawEvent::Undelegated(_sender, kind));
+            Ok(())
+        }
+
+        /// Release the sender's conviction locks that have expired, returning
+        /// the balance no other lock still holds.
+        pub fn unlock(origin) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let locks = Self::locks_of(&_sender);
+            ensure!(!locks.is_empty(), "Account is not locked");
+            let now = <system::Module<T>>::block_number();
+            let (expired, held): (Vec<LockOf<T>>, Vec<LockOf<T>>) = locks.into_iter()
+                .partition(|&(_, _, until)| until.map_or(false, |until| until <= now));
+            ensure!(!expired.is_empty(), "Lock has not expired");
+            Self::set_locks(&_sender, held)?;
+            Self::deposit_event(RawEvent::Unlocked(_sender));
             Ok(())
         }
     }
 }
 
+impl<T: Trait> Module<T> {
+    /// Move every active proposal whose current stage ends at or before
+    /// `now` on to its next stage.
+    pub fn end_block(now: T::BlockNumber) -> Result {
+        let (due, pending): (Vec<_>, Vec<_>) = Self::active_proposals().into_iter()
+            .partition(|&(_, ends_at)| ends_at <= now);
+        <ActiveProposals<T>>::put(pending);
+
+        for (proposal_hash, _) in due {
+            // a missing record must not hold up the proposals due after it,
+            // which have already been taken off the active list
+            let record = match <ProposalOf<T>>::get(&proposal_hash) {
+                Some(record) => record,
+                None => {
+                    runtime_io::print("Active proposal does not exist");
+                    continue;
+                },
+            };
+            match record.stage {
+                ProposalStage::PreVoting => Self::start_voting(proposal_hash, record, now, T::VOTING_PERIOD),
+                ProposalStage::Voting if record.secret_ballot => Self::start_reveal(proposal_hash, record, now),
+                ProposalStage::Voting | ProposalStage::Revealing => Self::complete_voting(proposal_hash, record),
+                ProposalStage::Completed | ProposalStage::Cancelled | ProposalStage::Withdrawn | ProposalStage::Vetoed => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a proposal has not yet completed or been stopped.
+    fn ensure_open(record: &ProposalRecordOf<T>) -> Result {
+        match record.stage {
+            ProposalStage::PreVoting | ProposalStage::Voting | ProposalStage::Revealing => Ok(()),
+            ProposalStage::Completed => Err("Proposal already completed"),
+            ProposalStage::Cancelled => Err("Proposal was cancelled"),
+            ProposalStage::Withdrawn => Err("Proposal was withdrawn"),
+            ProposalStage::Vetoed => Err("Proposal was vetoed"),
+        }
+    }
+
+    /// Stop an open proposal from advancing, releasing its preimages and
+    /// the balance locked behind its conviction votes, and returning any
+    /// quadratic vote credits and secret ballot commitment deposits held
+    /// for it.
+    fn close_proposal(proposal_hash: T::Hash, record: &ProposalRecordOf<T>) {
+        <ActiveProposals<T>>::mutate(|active| active.retain(|&(hash, _)| hash != proposal_hash));
+        Self::release_preimages(record);
+        for voter in Self::proposal_voters(proposal_hash) {
+            Self::release_vote_lock(&voter, proposal_hash);
+        }
+        if record.scheme == VotingScheme::Quadratic {
+            Self::return_credits(proposal_hash);
+        }
+        if record.secret_ballot {
+            Self::settle_commitments(proposal_hash, false);
+        }
+    }
+
+    fn start_voting(proposal_hash: T::Hash, record: ProposalRecordOf<T>, now: T::BlockNumber, period: T::BlockNumber) {
+        let mut new_record = record;
+        new_record.stage = ProposalStage::Voting;
+        new_record.voting_starts_at = now;
+        new_record.voting_ends_at = now + period;
+        <ActiveProposals<T>>::mutate(|active| active.push((proposal_hash, new_record.voting_ends_at)));
+        <ProposalOf<T>>::insert(&proposal_hash, new_record);
+        Self::deposit_event(RawEvent::VotingStarted(proposal_hash));
+    }
+
+    fn start_reveal(proposal_hash: T::Hash, record: ProposalRecordOf<T>, now: T::BlockNumber) {
+        let mut new_record = record;
+        new_record.stage = ProposalStage::Revealing;
+        <ActiveProposals<T>>::mutate(|active| active.push((proposal_hash, now + T::REVEAL_PERIOD)));
+        <ProposalOf<T>>::insert(&proposal_hash, new_record);
+        Self::deposit_event(RawEvent::RevealStarted(proposal_hash));
+    }
+
+    fn complete_voting(proposal_hash: T::Hash, record: ProposalRecordOf<T>) {
+        // count the votes and record the result on the proposal, keeping the
+        // balance locked behind them for their lock periods from now on
+        let ballots = Self::ballots_of(proposal_hash, &record);
+        let (totals, rounds, turnout) = Self::count_votes(&record, &ballots);
+        let (outcome, winning_choice) = Self::outcome_of(&record, &totals, turnout);
+        Self::lock_voters(proposal_hash);
+        match record.scheme {
+            VotingScheme::RankedChoice => <RoundsOf<T>>::insert(proposal_hash, rounds),
+            VotingScheme::Quadratic => Self::return_credits(proposal_hash),
+            VotingScheme::SingleChoice | VotingScheme::Approval => (),
+        }
+        if record.secret_ballot {
+            // commitments that were never revealed are left out of the tally
+            // and forfeit their deposits
+            Self::settle_commitments(proposal_hash, true);
+        }
+        let mut new_record = record;
+        new_record.stage = ProposalStage::Completed;
+        new_record.outcome = Some(outcome);
+        new_record.winning_choice = winning_choice;
+        <TallyOf<T>>::insert(proposal_hash, totals.clone());
+
+        // a proposal that ran its course gets its deposit back, whatever the outcome
+        <balances::Module<T>>::unreserve(&new_record.author, new_record.deposit);
+        let (author, deposit) = (new_record.author.clone(), new_record.deposit);
+        let category = new_record.category.clone();
+        Self::release_preimages(&new_record);
+        <ProposalOf<T>>::insert(&proposal_hash, new_record);
+        Self::deposit_event(RawEvent::VotingCompleted(proposal_hash, outcome, totals));
+        Self::deposit_event(RawEvent::DepositReturned(proposal_hash, author, deposit));
+
+        if outcome == ProposalOutcome::Passed {
+            Self::enact_proposal(proposal_hash, category);
+        }
+    }
+
+    /// Check that a proposal's contents are well formed and, if they are a
+    /// preimage, that it has been noted.
+    fn check_contents(contents: &ProposalContentsOf<T>) -> Result {
+        match *contents {
+            ProposalContents::Inline(ref body) => {
+                ensure!(!body.is_empty(), "Proposal must not be empty");
+                ensure!(body.len() <= MAX_INLINE_CONTENTS_LEN, "Inline contents are too long");
+            },
+            ProposalContents::Preimage(ref hash) => {
+                ensure!(<PreimageOf<T>>::exists(hash), "Proposal contents have not been noted");
+            },
+            ProposalContents::Uri(_, ref uri) => {
+                ensure!(is_valid_uri(uri), "Invalid contents URI");
+            },
+            ProposalContents::Ipfs(ref cid) => {
+                ensure!(is_valid_cid(cid), "Invalid IPFS CID");
+            },
+        }
+        Ok(())
+    }
+
+    /// The hash identifying a proposal's contents, wherever they are stored.
+    fn contents_hash(contents: &ProposalContentsOf<T>) -> T::Hash {
+        match *contents {
+            ProposalContents::Inline(ref body) => T::Hashing::hash(&body[..]),
+            ProposalContents::Preimage(hash) | ProposalContents::Uri(hash, _) => hash,
+            ProposalContents::Ipfs(ref cid) => T::Hashing::hash(&cid[..]),
+        }
+    }
+
+    /// Count an open proposal as a user of its contents and upgrade code.
+    fn use_preimages(record: &ProposalRecordOf<T>) {
+        if let ProposalContents::Preimage(hash) = record.contents {
+            Self::use_preimage(hash);
+        }
+        if let ProposalCategory::Upgrade(code_hash) = record.category {
+            Self::use_preimage(code_hash);
+        }
+    }
+
+    /// Stop counting a closed proposal as a user of its contents and upgrade
+    /// code, so that they can be reaped.
+    fn release_preimages(record: &ProposalRecordOf<T>) {
+        if let ProposalContents::Preimage(hash) = record.contents {
+            Self::release_preimage(hash);
+        }
+        if let ProposalCategory::Upgrade(code_hash) = record.category {
+            Self::release_preimage(code_hash);
+        }
+    }
+
+    fn use_preimage(hash: T::Hash) {
+        <PreimageUsers<T>>::mutate(hash, |users| *users += 1);
+    }
+
+    fn release_preimage(hash: T::Hash) {
+        <PreimageUsers<T>>::mutate(hash, |users| *users = users.saturating_sub(1));
+    }
+
+    /// Carry out the action requested by a passed proposal.
+    fn enact_proposal(proposal_hash: T::Hash, category: ProposalCategoryOf<T>) {
+        match category {
+            ProposalCategory::Funding(amount, beneficiary) => {
+                // an account left below the existential deposit is reaped,
+                // destroying its balance, so neither the treasury nor the
+                // beneficiary may end up there
+                let pot = Self::pot();
+                let existential_deposit = <balances::Module<T>>::existential_deposit();
+                if pot < amount || pot - amount < existential_deposit
+                    || <balances::Module<T>>::free_balance(&beneficiary) + amount < existential_deposit {
+                    Self::deposit_event(RawEvent::FundingFailed(proposal_hash));
+                    return;
+                }
+                // move the funds out of the treasury account, leaving the
+                // total issuance unchanged
+                <balances::Module<T>>::set_free_balance(&Self::treasury_account(), pot - amount);
+                <balances::Module<T>>::increase_free_balance_creating(&beneficiary, amount);
+                Self::deposit_event(RawEvent::FundingPaid(proposal_hash, beneficiary, amount));
+            },
+            ProposalCategory::Upgrade(code_hash) => {
+                // the same write performed by the consensus module's `set_code`
+                match Self::preimage_of(&code_hash) {
+                    Some((code, _, _)) => {
+                        runtime_io::set_storage(well_known_keys::CODE, &code);
+                        Self::deposit_event(RawEvent::RuntimeUpgraded(proposal_hash, code_hash));
+                    },
+                    None => Self::deposit_event(RawEvent::UpgradeFailed(proposal_hash)),
+                }
+            },
+            ProposalCategory::Call(encoded) => {
+                let ok = match T::Proposal::decode(&mut &encoded[..]) {
+                    Some(call) => call.dispatch(system::RawOrigin::Root.into()).is_ok(),
+                    None => false,
+                };
+                Self::deposit_event(RawEvent::CallDispatched(proposal_hash, ok));
+            },
+            ProposalCategory::Signaling => (),
+        }
+    }
+
+    /// Check that a vote makes valid choices under a proposal's voting scheme.
+    fn check_vote(record: &ProposalRecordOf<T>, vote: &Vote) -> Result {
+        ensure!(!vote.choices.is_empty(), "Vote must make a choice");
+        ensure!(vote.choices.iter().all(|&choice| choice < record.choice_count()), "Invalid choice");
+        match record.scheme {
+            VotingScheme::SingleChoice => {
+                ensure!(vote.choices.len() == 1, "Vote must make exactly one choice");
+            },
+            VotingScheme::Quadratic => {
+                ensure!(vote.choices.len() == 1, "Vote must make exactly one choice");
+                // credits already set a quadratic vote's weight
+                ensure!(vote.conviction == Conviction::None, "Quadratic votes cannot carry conviction");
+            },
+            VotingScheme::Approval | VotingScheme::RankedChoice => {
+                let mut distinct = vote.choices.clone();
+                distinct.sort();
+                distinct.dedup();
+                ensure!(distinct.len() == vote.choices.len(), "Vote must not repeat a choice");
+            },
+        }
+        Ok(())
+    }
+
+    /// Return the credits reserved by every voter on a quadratic proposal.
+    fn return_credits(proposal_hash: T::Hash) {
+        for voter in Self::proposal_voters(proposal_hash) {
+            <balances::Module<T>>::unreserve(&voter, Self::credits_of((proposal_hash, voter.clone())));
+            <CreditsOf<T>>::remove((proposal_hash, voter));
+        }
+    }
+
+    /// Return or slash the deposits of commitments on a secret ballot that
+    /// were never revealed.
+    fn settle_commitments(proposal_hash: T::Hash, slash: bool) {
+        for committer in Self::proposal_committers(proposal_hash) {
+            if Self::vote_of((proposal_hash, committer.clone())).is_some() {
+                continue;
+            }
+            if let Some((_, deposit)) = Self::commitment_of((proposal_hash, committer.clone())) {
+                if slash {
+                    let remaining = <balances::Module<T>>::slash_reserved(&committer, deposit);
+                    let slashed = deposit - remaining.unwrap_or(Zero::zero());
+                    T::DepositSink::on_deposit_slashed(slashed);
+                    Self::deposit_event(RawEvent::CommitmentSlashed(proposal_hash, committer, slashed));
+                } else {
+                    <balances::Module<T>>::unreserve(&committer, deposit);
+                }
+            }
+        }
+    }
+
+    /// Count the stake behind the votes cast on a proposal, multiplied by
+    /// each voter's conviction, returning the final total for each choice.
+    /// Stake is read when the votes are counted, so that balance moved to
+    /// another account after voting is counted only once.
+    pub fn tally(proposal_hash: T::Hash) -> Vec<T::Balance> {
+        match Self::proposal_of(proposal_hash) {
+            Some(record) => Self::count_votes(&record, &Self::ballots_of(proposal_hash, &record)).0,
+            None => vec![],
+        }
+    }
+
+    /// Gather the ballots counted on a proposal: one for every voter and,
+    /// unless the proposal is quadratic, one for every account whose vote
+    /// they cast by delegation, with the conviction it delegates with.
+    /// Conviction votes and delegations count with the balance locked behind
+    /// them, and others with the account's stake, read when the ballots are
+    /// gathered. Quadratic votes count with the square root of their credits
+    /// in place of stake.
+    fn ballots_of(proposal_hash: T::Hash, record: &ProposalRecordOf<T>) -> Vec<BallotOf<T>> {
+        let mut ballots = Vec::new();
+        for voter in Self::proposal_voters(proposal_hash) {
+            if let Some(vote) = Self::vote_of((proposal_hash, voter.clone())) {
+                if record.scheme == VotingScheme::Quadratic {
+                    let weight = Self::credits_of((proposal_hash, voter.clone())).integer_sqrt();
+                    ballots.push((voter, vote.choices, weight, vote.conviction));
+                    continue;
+                }
+                for (delegator, conviction, locked) in Self::delegators_voting_with(proposal_hash, &voter, record.category.kind()) {
+                    let stake = if conviction == Conviction::None { Self::stake_of(&delegator) } else { locked };
+                    ballots.push((delegator, vote.choices.clone(), stake, conviction));
+                }
+                let stake = if vote.conviction == Conviction::None {
+                    Self::stake_of(&voter)
+                } else {
+                    Self::vote_lock_of(&voter, proposal_hash)
+                };
+                ballots.push((voter, vote.choices, stake, vote.conviction));
+            }
+        }
+        ballots
+    }
+
+    /// Count a proposal's ballots under its voting scheme, returning the
+    /// final total for each choice, the totals after every round of
+    /// counting, and the turnout: the stake behind the ballots, without
+    /// any conviction multiplier, or the total weight of quadratic votes.
+    fn count_votes(record: &ProposalRecordOf<T>, ballots: &[BallotOf<T>]) -> (Vec<T::Balance>, Vec<Vec<T::Balance>>, T::Balance) {
+        let choice_count = record.choice_count() as usize;
+        let turnout = ballots.iter().fold(T::Balance::zero(), |sum, &(_, _, stake, _)| sum + stake);
+        let weighted: Vec<(Vec<u32>, T::Balance)> = ballots.iter()
+            .map(|&(_, ref choices, stake, conviction)| (choices.clone(), stake * T::Balance::sa(conviction.multiplier())))
+            .collect();
+
+        let rounds = match record.scheme {
+            VotingScheme::RankedChoice => Self::instant_runoff(&weighted, choice_count),
+            VotingScheme::SingleChoice | VotingScheme::Approval | VotingScheme::Quadratic => {
+                // every choice on a ballot receives the ballot's full weight
+                let mut totals: Vec<T::Balance> = vec![Zero::zero(); choice_count];
+                for &(ref choices, weight) in weighted.iter() {
+                    for &choice in choices.iter() {
+                        if let Some(total) = totals.get_mut(choice as usize) {
+                            *total = *total + weight;
+                        }
+                    }
+                }
+                vec![totals]
+            },
+        };
+        let totals = rounds.last().cloned().unwrap_or_else(Vec::new);
+        (totals, rounds, turnout)
+    }
+
+    /// The rules a kind of proposal needs to pass, falling back to
+    /// `CategoryRules::fallback` when none are configured.
+    pub fn rules_for(kind: ProposalKind) -> CategoryRules {
+        Self::category_rules(kind).unwrap_or_else(|| CategoryRules::fallback(kind))
+    }
+
+    /// The funds available for paying out passed Funding proposals.
+    pub fn pot() -> T::Balance {
+        <balances::Module<T>>::free_balance(&Self::treasury_account())
+    }
+
+    /// The stake an account votes with without conviction: its free balance
+    /// and the balance reserved by its conviction locks.
+    pub fn stake_of(who: &T::AccountId) -> T::Balance {
+        <balances::Module<T>>::free_balance(who) + Self::locked_balance(who)
+    }
+
+    /// The balance an account has reserved for its conviction locks, the
+    /// largest that any of them holds.
+    pub fn locked_balance(who: &T::AccountId) -> T::Balance {
+        Self::locks_of(who).into_iter()
+            .fold(Zero::zero(), |locked, (_, balance, _)| rstd::cmp::max(locked, balance))
+    }
+
+    /// The balance an account has locked behind its conviction vote on a
+    /// proposal.
+    fn vote_lock_of(who: &T::AccountId, proposal_hash: T::Hash) -> T::Balance {
+        Self::locks_of(who).into_iter()
+            .find(|&(reason, _, _)| reason == LockReason::Vote(proposal_hash))
+            .map_or_else(Zero::zero, |(_, balance, _)| balance)
+    }
+
+    /// The delegate an account's votes on a kind of proposal go to, falling
+    /// back to its default delegate, with the conviction it delegates with
+    /// and the balance locked behind the delegation.
+    pub fn delegate_for(who: &T::AccountId, kind: ProposalKind) -> Option<(T::AccountId, Conviction, T::Balance)> {
+        Self::delegation_of((who.clone(), Some(kind))).or_else(|| Self::delegation_of((who.clone(), None)))
+    }
+
+    /// Whether following the delegates for a kind of proposal from `from`
+    /// leads to `to`.
+    fn delegates_to(from: &T::AccountId, to: &T::AccountId, kind: ProposalKind) -> bool {
+        let mut next = Some(from.clone());
+        while let Some(account) = next {
+            if account == *to {
+                return true;
+            }
+            next = Self::delegate_for(&account, kind).map(|(delegate, _, _)| delegate);
+        }
+        false
+    }
+
+    /// The accounts whose votes on a kind of proposal are cast by `voter`
+    /// through delegation, directly or along a chain of delegates, with the
+    /// conviction each delegates with and the balance locked behind it.
+    /// Delegators that voted themselves are left out, along with the accounts
+    /// delegating through them.
+    fn delegators_voting_with(proposal_hash: T::Hash, voter: &T::AccountId, kind: ProposalKind) -> Vec<(T::AccountId, Conviction, T::Balance)> {
+        let mut found = Vec::new();
+        let mut pending = vec![voter.clone()];
+        while let Some(delegate) = pending.pop() {
+            let delegators = Self::delegators_of((delegate.clone(), Some(kind))).into_iter()
+                .chain(Self::delegators_of((delegate.clone(), None)));
+            for delegator in delegators {
+                if Self::vote_of((proposal_hash, delegator.clone())).is_some() {
+                    continue;
+                }
+                // default delegators only count if the kind is not delegated elsewhere
+                if let Some((to, conviction, locked)) = Self::delegate_for(&delegator, kind) {
+                    if to == delegate && !found.iter().any(|&(ref account, _, _)| *account == delegator) {
+                        pending.push(delegator.clone());
+                        found.push((delegator, conviction, locked));
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Count ranked ballots by instant runoff. Each round, every ballot backs
+    /// its highest ranked choice that is still standing. Counting stops once
+    /// a choice has a majority of the ballots still in play or the remaining
+    /// choices are tied; otherwise the choice with the least support is
+    /// eliminated, the later choice going first when several are tied.
+    fn instant_runoff(ballots: &[(Vec<u32>, T::Balance)], choice_count: usize) -> Vec<Vec<T::Balance>> {
+        let mut eliminated = vec![false; choice_count];
+        let mut rounds = Vec::new();
+        loop {
+            let mut totals: Vec<T::Balance> = vec![Zero::zero(); choice_count];
+            for &(ref ranking, weight) in ballots.iter() {
+                let standing = ranking.iter().map(|&choice| choice as usize)
+                    .find(|&choice| choice < choice_count && !eliminated[choice]);
+                if let Some(choice) = standing {
+                    totals[choice] = totals[choice] + weight;
+                }
+            }
+            let in_play = totals.iter().fold(T::Balance::zero(), |sum, total| sum + *total);
+            let remaining: Vec<usize> = (0..choice_count).filter(|&choice| !eliminated[choice]).collect();
+            let leader = remaining.iter().map(|&choice| totals[choice]).max().unwrap_or_else(Zero::zero);
+            let last = remaining.iter().rev().cloned().min_by_key(|&choice| totals[choice]);
+            rounds.push(totals.clone());
+
+            match last {
+                Some(last) if leader + leader <= in_play && totals[last] < leader => {
+                    eliminated[last] = true;
+                },
+                _ => break,
+            }
+        }
+        rounds
+    }
+
+    /// The block at which voting on a proposal is due to complete, once any
+    /// secret ballot has been revealed.
+    fn completes_at(record: &ProposalRecordOf<T>) -> T::BlockNumber {
+        if record.secret_ballot {
+            record.voting_ends_at + T::REVEAL_PERIOD
+        } else {
+            record.voting_ends_at
+        }
+    }
+
+    /// Check the balance to lock behind a vote or delegation: one with a
+    /// conviction must lock some balance, and one without cannot lock any.
+    fn check_lock(conviction: Conviction, balance: T::Balance) -> Result {
+        if conviction == Conviction::None {
+            ensure!(balance.is_zero(), "Only conviction votes lock balance");
+        } else {
+            ensure!(!balance.is_zero(), "Conviction votes must lock balance");
+        }
+        Ok(())
+    }
+
+    /// Lock the balance behind a conviction vote from the moment it is cast,
+    /// for as many lock periods as its conviction asks for after the
+    /// proposal is due to complete, replacing any lock behind the voter's
+    /// previous vote on the proposal.
+    fn lock_vote(who: &T::AccountId, proposal_hash: T::Hash, record: &ProposalRecordOf<T>, conviction: Conviction, balance: T::Balance) -> Result {
+        let mut locks = Self::locks_of(who);
+        locks.retain(|&(reason, _, _)| reason != LockReason::Vote(proposal_hash));
+        let periods = conviction.lock_periods();
+        if periods > 0 {
+            let until = Self::completes_at(record) + T::LOCK_PERIOD * T::BlockNumber::sa(periods);
+            locks.push((LockReason::Vote(proposal_hash), balance, Some(until)));
+        }
+        Self::set_locks(who, locks)
+    }
+
+    /// Release the balance locked behind an account's vote on a proposal
+    /// that will not be counted.
+    fn release_vote_lock(who: &T::AccountId, proposal_hash: T::Hash) {
+        let mut locks = Self::locks_of(who);
+        locks.retain(|&(reason, _, _)| reason != LockReason::Vote(proposal_hash));
+        // releasing a lock only ever unreserves balance, which cannot fail
+        let _ = Self::set_locks(who, locks);
+    }
+
+    /// Start the lock periods of a delegation that is being replaced or
+    /// removed, keeping its balance locked for as many lock periods as its
+    /// conviction asks for from now.
+    fn release_delegation_lock(locks: &mut Vec<LockOf<T>>, kind: Option<ProposalKind>, conviction: Conviction) {
+        let until = <system::Module<T>>::block_number() + T::LOCK_PERIOD * T::BlockNumber::sa(conviction.lock_periods());
+        for lock in locks.iter_mut() {
+            if lock.0 == LockReason::Delegation(kind) && lock.2.is_none() {
+                lock.2 = Some(until);
+            }
+        }
+    }
+
+    /// Keep the balance behind every conviction vote on a completed proposal
+    /// locked for as many lock periods as its conviction asks for from now.
+    /// Delegations hold their own locks, and quadratic voters staked credits
+    /// rather than conviction.
+    fn lock_voters(proposal_hash: T::Hash) {
+        let now = <system::Module<T>>::block_number();
+        for voter in Self::proposal_voters(proposal_hash) {
+            let periods = match Self::vote_of((proposal_hash, voter.clone())) {
+                Some(vote) => vote.conviction.lock_periods(),
+                None => continue,
+            };
+            if periods == 0 {
+                continue;
+            }
+            let until = now + T::LOCK_PERIOD * T::BlockNumber::sa(periods);
+            <LocksOf<T>>::mutate(&voter, |locks| {
+                for lock in locks.iter_mut() {
+                    if lock.0 == LockReason::Vote(proposal_hash) {
+                        lock.2 = Some(until);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Replace an account's conviction locks, reserving or unreserving the
+    /// difference in the balance they hold. A lock may not leave the account
+    /// less free balance than the existential deposit and the transaction
+    /// base fee, so that it can still pay to unlock.
+    fn set_locks(who: &T::AccountId, locks: Vec<LockOf<T>>) -> Result {
+        let locked = Self::locked_balance(who);
+        let to_lock = locks.iter().fold(Zero::zero(), |max, &(_, balance, _)| rstd::cmp::max(max, balance));
+        if to_lock > locked {
+            let extra = to_lock - locked;
+            let free = <balances::Module<T>>::free_balance(who);
+            let floor = <balances::Module<T>>::existential_deposit() + <balances::Module<T>>::transaction_base_fee();
+            ensure!(free >= extra && free - extra >= floor, "Balance too low to lock");
+            <balances::Module<T>>::reserve(who, extra).map_err(|_| "Balance too low to lock")?;
+        } else if to_lock < locked {
+            <balances::Module<T>>::unreserve(who, locked - to_lock);
+        }
+        if locks.is_empty() {
+            <LocksOf<T>>::remove(who);
+        } else {
+            <LocksOf<T>>::insert(who, locks);
+        }
+        Ok(())
+    }
+
+    /// Decide the outcome of a vote and its winning choice from its totals
+    /// under the rules for its kind of proposal, using the total issuance,
+    /// less the treasury's funds that no one can vote with, as the electorate. Quorum and the turnout bias of the threshold are
+    /// measured by the stake that voted, so that conviction cannot inflate
+    /// them. A vote that no stake participated in never has quorum.
+    ///
+    /// Quadratic votes are weighed in credits rather than stake, so they
+    /// cannot be measured against the total issuance and only need some
+    /// turnout and the approval rule.
+    ///
+    /// A yes/no ballot must meet the approval and threshold rules to pass. A
+    /// ballot with its own choices passes with whichever choice has the most
+    /// support, and is rejected if the top choices are tied.
+    fn outcome_of(record: &ProposalRecordOf<T>, totals: &[T::Balance], turnout: T::Balance) -> (ProposalOutcome, Option<u32>) {
+        let rules = Self::rules_for(record.category.kind());
+        let electorate = <balances::Module<T>>::total_issuance() - Self::pot();
+        let quadratic = record.scheme == VotingScheme::Quadratic;
+        if turnout.is_zero() || (!quadratic && turnout < rules.min_turnout * electorate) {
+            return (ProposalOutcome::NoQuorum, None);
+        }
+
+        if record.choices.is_empty() {
+            let (yes, no) = (totals[AYE as usize], totals[NAY as usize]);
+            let approved = quadratic || rules.threshold.approved(yes, no, turnout, electorate);
+            if yes > rules.approval * (yes + no) && approved {
+                (ProposalOutcome::Passed, Some(AYE))
+            } else {
+                (ProposalOutcome::Rejected, Some(NAY))
+            }
+        } else {
+            match Self::plurality(totals) {
+                Some(choice) => (ProposalOutcome::Passed, Some(choice)),
+                None => (ProposalOutcome::Rejected, None),
+            }
+        }
+    }
+
+    /// The index of the largest total, or `None` if it is tied.
+    fn plurality(totals: &[T::Balance]) -> Option<u32> {
+        let mut winner = None;
+        let mut best = T::Balance::zero();
+        let mut tied = false;
+        for (choice, total) in totals.iter().enumerate() {
+            if winner.is_none() || *total > best {
+                winner = Some(choice as u32);
+                best = *total;
+                tied = false;
+            } else if *total == best {
+                tied = true;
+            }
+        }
+        if tied { None } else { winner }
+    }
+}
+
+/// Slashed proposal deposits can be routed into the treasury account by
+/// setting the runtime's `DepositSink` to this module.
+impl<T: Trait> OnDepositSlashed<T::Balance> for Module<T> {
+    fn on_deposit_slashed(amount: T::Balance) {
+        // slashing took the amount out of the total issuance, which it
+        // rejoins along with the treasury
+        <balances::Module<T>>::increase_free_balance_creating(&Self::treasury_account(), amount);
+        <balances::Module<T>>::increase_total_stake_by(amount);
+    }
+}
+
 decl_event!(
     pub enum Event<T> where <T as system::Trait>::Hash,
-                            <T as system::Trait>::AccountId {
-        NewProposal(AccountId, Hash),
-        NewComment(AccountId, Hash),
+                            <T as system::Trait>::AccountId,
+                            <T as system::Trait>::BlockNumber,
+                            <T as balances::Trait>::Balance {
+        NewProposal(AccountId, u32, Hash),
+        NewComment(AccountId, Hash, u32),
+        CommentEdited(AccountId, Hash, u32),
+        CommentDeleted(AccountId, Hash, u32),
         VotingStarted(Hash),
-        VoteSubmitted(Hash, AccountId, bool),
-        VotingCompleted(Hash),
+        VoteSubmitted(Hash, AccountId, Vote),
+        VoteChanged(Hash, AccountId, Vote),
+        VoteRetracted(Hash, AccountId),
+        VoteCommitted(Hash, AccountId),
+        RevealStarted(Hash),
+        VoteRevealed(Hash, AccountId, Vote),
+        CommitmentSlashed(Hash, AccountId, Balance),
+        Unlocked(AccountId),
+        Delegated(AccountId, AccountId, Option<ProposalKind>),
+        Undelegated(AccountId, Option<ProposalKind>),
+        VotingCompleted(Hash, ProposalOutcome, Vec<Balance>),
+        ProposalEdited(Hash, u32),
+        ProposalCancelled(Hash),
+        ProposalWithdrawn(Hash),
+        ProposalFastTracked(Hash),
+        ProposalVetoed(Hash, BlockNumber),
+        DepositReserved(Hash, AccountId, Balance),
+        DepositReturned(Hash, AccountId, Balance),
+        DepositSlashed(Hash, AccountId, Balance),
+        FundingPaid(Hash, AccountId, Balance),
+        FundingFailed(Hash),
+        PreimageNoted(Hash, AccountId),
+        PreimageReaped(Hash, AccountId),
+        RuntimeUpgraded(Hash, Hash),
+        UpgradeFailed(Hash),
+        CallDispatched(Hash, bool),
     }
 );
 
@@ -174,8 +1641,48 @@ decl_storage! {
     trait Store for Module<T: Trait> as Governance {
         pub ProposalCount get(proposal_count) : u32;
         pub Proposals get(proposals): Vec<T::Hash>;
-        pub ProposalOf get(proposal_of): map T::Hash => Option<ProposalRecord<T::AccountId>>;
+        pub ProposalOf get(proposal_of): map T::Hash => Option<ProposalRecordOf<T>>;
+        /// The hash of each proposal by its index, which dispatchables address proposals by
+        pub ProposalHashOf get(proposal_hash_of): map u32 => Option<T::Hash>;
+        /// Every revision of each proposal's title and contents, starting from revision 0
+        pub RevisionOf get(revision_of): map (T::Hash, u32) => Option<ProposalRevisionOf<T>>;
+        /// Proposals that have not yet completed, with the block at which their current stage ends
+        pub ActiveProposals get(active_proposals): Vec<(T::Hash, T::BlockNumber)>;
+        pub CommentCount get(comment_count): map T::Hash => u32;
+        pub CommentOf get(comment_of): map (T::Hash, u32) => Option<CommentRecordOf<T>>;
         pub ProposalVoters get(proposal_voters): map T::Hash => Vec<T::AccountId>;
-        pub VoteOf get(vote_of): map (T::Hash, T::AccountId) => Option<bool>;
+        pub VoteOf get(vote_of): map (T::Hash, T::AccountId) => Option<Vote>;
+        /// The credits each voter reserved for their vote on a quadratic proposal
+        pub CreditsOf get(credits_of): map (T::Hash, T::AccountId) => T::Balance;
+        /// Accounts that have committed to a vote on a secret ballot
+        pub ProposalCommitters get(proposal_committers): map T::Hash => Vec<T::AccountId>;
+        /// The hash of each committed vote with its voter and salt, with the deposit reserved for it
+        pub CommitmentOf get(commitment_of): map (T::Hash, T::AccountId) => Option<(T::Hash, T::Balance)>;
+        /// The total for each choice on a completed proposal
+        pub TallyOf get(tally_of): map T::Hash => Vec<T::Balance>;
+        /// The totals after each round of counting a completed ranked choice proposal
+        pub RoundsOf get(rounds_of): map T::Hash => Vec<Vec<T::Balance>>;
+        /// The account each account delegates its votes on a kind of proposal to, or by default for
+        /// `None`, with the conviction it delegates with and the balance it locks behind the delegation
+        pub DelegationOf get(delegation_of): map (T::AccountId, Option<ProposalKind>) => Option<(T::AccountId, Conviction, T::Balance)>;
+        /// The accounts delegating their votes on a kind of proposal, or by default, directly to each account
+        pub DelegatorsOf get(delegators_of): map (T::AccountId, Option<ProposalKind>) => Vec<T::AccountId>;
+        /// The conviction locks on each account's balance. The same balance may back several votes
+        /// and delegations, so the account has the largest of them reserved
+        pub LocksOf get(locks_of): map T::AccountId => Vec<LockOf<T>>;
+        pub ProposalDeposit get(proposal_deposit) config(): T::Balance;
+        /// Reserved for each vote committed to a secret ballot until it is revealed
+        pub CommitmentDeposit get(commitment_deposit) config(): T::Balance;
+        /// The account passed Funding proposals are paid from, which no one should hold the key to
+        pub TreasuryAccount get(treasury_account) config(): T::AccountId;
+        pub CategoryRulesOf get(category_rules) config(): map ProposalKind => Option<CategoryRules>;
+        /// The block until which vetoed contents, by hash, cannot be proposed again
+        pub VetoedUntil get(vetoed_until): map T::Hash => Option<T::BlockNumber>;
+        /// Noted preimages by hash, with the account that noted them and the deposit reserved for them
+        pub PreimageOf get(preimage_of): map T::Hash => Option<(Vec<u8>, T::AccountId, T::Balance)>;
+        /// The number of open proposals that refer to each preimage
+        pub PreimageUsers get(preimage_users): map T::Hash => u32;
+        /// Reserved for each byte of a noted preimage
+        pub PreimageByteDeposit get(preimage_byte_deposit) config(): T::Balance;
     }
 }
@@ -40,12 +40,14 @@ extern crate srml_support as runtime_support;
 extern crate sr_primitives as runtime_primitives;
 extern crate sr_io as runtime_io;
 extern crate srml_system as system;
+extern crate srml_balances as balances;
 
 use codec::Encode;
 use rstd::prelude::*;
 use runtime_support::dispatch::Result;
 
 pub mod governance;
+pub mod vote_threshold;
 pub use governance::{Module, Trait, RawEvent, Event};
 
 #[cfg(test)]
@@ -58,7 +60,7 @@ mod tests {
     // The testing primitives are very useful for avoiding having to work with signatures
     // or public keys. `u64` is used as the `AccountId` and no `Signature`s are requried.
     use runtime_primitives::{
-        BuildStorage, traits::{BlakeTwo256}, testing::{Digest, DigestItem, Header}
+        BuildStorage, Permill, traits::{BlakeTwo256}, testing::{Digest, DigestItem, Header}
     };
 
     impl_outer_origin! {
@@ -67,12 +69,16 @@ mod tests {
 
     impl_outer_event! {
         pub enum Event for Test {
+            balances<T>,
             governance<T>,
         }
     }
 
     impl_outer_dispatch! {
-        pub enum Call for Test where origin: Origin {}
+        pub enum Call for Test where origin: Origin {
+            balances::Balances,
+            governance::Governance,
+        }
     }
 
     // For testing the module, we construct most of a mock runtime. This means
@@ -93,39 +99,179 @@ mod tests {
         type Log = DigestItem;
     }
 
+    impl balances::Trait for Test {
+        type Balance = u64;
+        type AccountIndex = u64;
+        type OnFreeBalanceZero = ();
+        type EnsureAccountLiquid = ();
+        type Event = Event;
+    }
+
     impl Trait for Test {
         type Event = Event;
+        const PRE_VOTING_PERIOD: u64 = 10;
+        const MIN_PRE_VOTING_PERIOD: u64 = 2;
+        const VOTING_PERIOD: u64 = 10;
+        const REVEAL_PERIOD: u64 = 10;
+        const LOCK_PERIOD: u64 = 10;
+        const FAST_TRACK_VOTING_PERIOD: u64 = 3;
+        const VETO_COOLOFF_PERIOD: u64 = 20;
+        type DepositSink = Governance;
+        type FastTrackOrigin = TechnicalCommittee;
+        type VetoOrigin = governance::EnsureRoot<H256>;
+        type CancelOrigin = governance::EnsureRoot<H256>;
+        type Proposal = Call;
+    }
+
+    /// Stands in for a technical committee collective, with the other test
+    /// key as its only member.
+    pub struct TechnicalCommittee;
+
+    impl governance::EnsureOrigin<Origin> for TechnicalCommittee {
+        fn ensure_origin(origin: Origin) -> super::Result {
+            let who = system::ensure_signed(origin)?;
+            ensure!(who == get_other_key(), "Origin is not the technical committee");
+            Ok(())
+        }
     }
 
     pub type System = system::Module<Test>;
+    pub type Balances = balances::Module<Test>;
     pub type Governance = Module<Test>;
 
     fn new_test_ext() -> sr_io::TestExternalities<Blake2Hasher> {
-        let t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+        new_test_ext_with_fees(0, 0)
+    }
+
+    /// Build the mock runtime with an existential deposit and transaction
+    /// base fee, which a live chain sets but most tests leave at zero.
+    fn new_test_ext_with_fees(existential_deposit: u64, transaction_base_fee: u64) -> sr_io::TestExternalities<Blake2Hasher> {
+        let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
         // We use default for brevity, but you can configure as desired if needed.
+        t.extend(balances::GenesisConfig::<Test>{
+            balances: vec![(get_test_key(), 10), (get_other_key(), 20), (get_treasury_key(), 100)],
+            transaction_base_fee: transaction_base_fee,
+            transaction_byte_fee: 0,
+            existential_deposit: existential_deposit,
+            transfer_fee: 0,
+            creation_fee: 0,
+            reclaim_rebate: 0,
+        }.build_storage().unwrap().0);
+        t.extend(governance::GenesisConfig::<Test>{
+            proposal_deposit: 1,
+            preimage_byte_deposit: 0,
+            commitment_deposit: 1,
+            treasury_account: get_treasury_key(),
+            category_rules: vec![(governance::ProposalKind::Upgrade, governance::CategoryRules {
+                min_turnout: Permill::from_percent(40),
+                approval: Permill::from_percent(66),
+                threshold: vote_threshold::VoteThreshold::SimpleMajority,
+            }), (governance::ProposalKind::Call, governance::CategoryRules {
+                min_turnout: Permill::from_percent(0),
+                approval: Permill::from_percent(50),
+                threshold: vote_threshold::VoteThreshold::SimpleMajority,
+            })],
+        }.build_storage().unwrap().0);
         t.into()
     }
 
-    fn propose(who: H256, title: &[u8], proposal: &[u8], category: governance::ProposalCategory) -> super::Result {
-        Governance::create_proposal(Origin::signed(who), title.to_vec(), proposal.to_vec(), category)
+    /// Note the preimage of a proposal's contents, unless it already has
+    /// been, and refer to it by its hash.
+    fn note_contents(who: H256, contents: &[u8]) -> governance::ProposalContents<H256> {
+        let hash = Blake2Hasher::hash(contents);
+        if Governance::preimage_of(hash).is_none() {
+            let _ = Governance::note_preimage(Origin::signed(who), contents.to_vec());
+        }
+        governance::ProposalContents::Preimage(hash)
+    }
+
+    fn propose(who: H256, title: &[u8], proposal: &[u8], category: governance::ProposalCategoryOf<Test>) -> super::Result {
+        Governance::create_proposal(Origin::signed(who), title.to_vec(), note_contents(who, proposal), category, vec![],
+                                    governance::VotingScheme::SingleChoice, false)
+    }
+
+    fn propose_poll(who: H256, title: &[u8], proposal: &[u8], choices: &[&[u8]], scheme: governance::VotingScheme) -> super::Result {
+        let choices = choices.iter().map(|choice| choice.to_vec()).collect();
+        Governance::create_proposal(Origin::signed(who), title.to_vec(), note_contents(who, proposal), governance::ProposalCategory::Signaling, choices, scheme, false)
+    }
+
+    fn submit_choice(who: H256, proposal_hash: H256, choice: u32) -> super::Result {
+        submit_choices(who, proposal_hash, &[choice])
+    }
+
+    fn submit_choices(who: H256, proposal_hash: H256, choices: &[u32]) -> super::Result {
+        Governance::submit_vote(Origin::signed(who), index_of(proposal_hash), governance::Vote { choices: choices.to_vec(), conviction: governance::Conviction::None }, 0)
     }
 
     fn add_comment(who: H256, proposal_hash: H256, comment: &[u8]) -> super::Result {
-        Governance::add_comment(Origin::signed(who), proposal_hash, comment.to_vec())
+        Governance::add_comment(Origin::signed(who), index_of(proposal_hash), comment.to_vec(), None)
+    }
+
+    fn reply_to_comment(who: H256, proposal_hash: H256, comment: &[u8], parent: u32) -> super::Result {
+        Governance::add_comment(Origin::signed(who), index_of(proposal_hash), comment.to_vec(), Some(parent))
+    }
+
+    fn edit_comment(who: H256, proposal_hash: H256, comment_id: u32, comment: &[u8]) -> super::Result {
+        Governance::edit_comment(Origin::signed(who), index_of(proposal_hash), comment_id, comment.to_vec())
+    }
+
+    fn delete_comment(who: H256, proposal_hash: H256, comment_id: u32) -> super::Result {
+        Governance::delete_comment(Origin::signed(who), index_of(proposal_hash), comment_id)
     }
 
+    /// Open voting on a proposal, first waiting out its minimum pre-voting
+    /// period if it has not yet passed.
     fn advance_proposal(who: H256, proposal_hash: H256) -> super::Result {
-        Governance::advance_proposal(Origin::signed(who), proposal_hash)
+        if let Some(record) = Governance::proposal_of(proposal_hash) {
+            let earliest = record.voting_starts_at - <Test as Trait>::PRE_VOTING_PERIOD + <Test as Trait>::MIN_PRE_VOTING_PERIOD;
+            if record.stage == governance::ProposalStage::PreVoting && System::block_number() < earliest {
+                System::set_block_number(earliest);
+            }
+        }
+        Governance::advance_proposal(Origin::signed(who), index_of(proposal_hash))
+    }
+
+    fn vote(aye: bool) -> governance::Vote {
+        let choice = if aye { governance::AYE } else { governance::NAY };
+        governance::Vote { choices: vec![choice], conviction: governance::Conviction::None }
+    }
+
+    fn submit_vote(who: H256, proposal_hash: H256, aye: bool) -> super::Result {
+        Governance::submit_vote(Origin::signed(who), index_of(proposal_hash), vote(aye), 0)
+    }
+
+    fn change_vote(who: H256, proposal_hash: H256, aye: bool) -> super::Result {
+        Governance::change_vote(Origin::signed(who), index_of(proposal_hash), vote(aye), 0)
     }
 
-    fn submit_vote(who: H256, proposal_hash: H256, vote: bool) -> super::Result {
-        Governance::submit_vote(Origin::signed(who), proposal_hash, vote)
+    fn retract_vote(who: H256, proposal_hash: H256) -> super::Result {
+        Governance::retract_vote(Origin::signed(who), index_of(proposal_hash))
     }
 
-    fn build_proposal_hash(who: H256, proposal: &[u8]) -> H256 {
+    fn propose_secret(who: H256, title: &[u8], proposal: &[u8]) -> super::Result {
+        Governance::create_proposal(Origin::signed(who), title.to_vec(), note_contents(who, proposal), governance::ProposalCategory::Signaling, vec![],
+                                    governance::VotingScheme::SingleChoice, true)
+    }
+
+    fn commitment(who: H256, aye: bool, salt: &[u8]) -> H256 {
+        Blake2Hasher::hash(&(who, vote(aye), salt.to_vec()).encode())
+    }
+
+    fn index_of(proposal_hash: H256) -> u32 {
+        Governance::proposal_of(proposal_hash).map_or(u32::max_value(), |record| record.index)
+    }
+
+    fn finish_voting(proposal_hash: H256) {
+        let voting_ends_at = Governance::proposal_of(proposal_hash).unwrap().voting_ends_at;
+        System::set_block_number(voting_ends_at);
+        assert_ok!(Governance::end_block(voting_ends_at));
+    }
+
+    fn build_proposal_hash(who: H256, proposal: &[u8], index: u32) -> H256 {
             let mut buf = Vec::new();
             buf.extend_from_slice(&who.encode());
-            buf.extend_from_slice(proposal.as_ref());
+            buf.extend_from_slice(&governance::ProposalContents::Preimage(Blake2Hasher::hash(proposal)).encode());
+            buf.extend_from_slice(&index.encode());
             return Blake2Hasher::hash(&buf[..]);
     }
 
@@ -135,6 +281,16 @@ mod tests {
         return public;
     }
 
+    fn get_other_key() -> H256 {
+        let pair: Pair = Pair::from_seed(&hex!("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f61"));
+        let public: H256 = pair.public().0.into();
+        return public;
+    }
+
+    fn get_treasury_key() -> H256 {
+        H256::from(100)
+    }
+
     fn generate_proposal() -> (&'static[u8], &'static[u8]) {
         let title: &[u8] = b"Make Edgeware Free";
         let proposal: &[u8] = b"Simple: make Edgeware free for everyone";
@@ -146,43 +302,71 @@ mod tests {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
             let public = get_test_key();
-            let category = governance::ProposalCategory::Funding(12);
+            let category = governance::ProposalCategory::Funding(12, get_other_key());
             let (title, proposal) = generate_proposal();
-            let hash = build_proposal_hash(public, &proposal);
-            assert_ok!(propose(public, title, proposal, category));
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category.clone()));
             assert_eq!(System::events(), vec![
                 EventRecord {
                     phase: Phase::ApplyExtrinsic(0),
-                    event: Event::governance(RawEvent::NewProposal(public, hash))
+                    event: Event::governance(RawEvent::PreimageNoted(Blake2Hasher::hash(proposal), public))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::NewProposal(public, 0, hash))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::DepositReserved(hash, public, 1))
                 }]
             );
 
             let title2: &[u8] = b"Proposal 2";
             let proposal2: &[u8] = b"Proposal 2";
-            let hash2 = build_proposal_hash(public, &proposal2);
+            let hash2 = build_proposal_hash(public, &proposal2, 1);
             assert_ok!(propose(public, title2, proposal2, category));
             assert_eq!(System::events(), vec![
                 EventRecord {
                     phase: Phase::ApplyExtrinsic(0),
-                    event: Event::governance(RawEvent::NewProposal(public, hash))
+                    event: Event::governance(RawEvent::PreimageNoted(Blake2Hasher::hash(proposal), public))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::NewProposal(public, 0, hash))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::DepositReserved(hash, public, 1))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::PreimageNoted(Blake2Hasher::hash(proposal2), public))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::NewProposal(public, 1, hash2))
                 },
                 EventRecord {
                     phase: Phase::ApplyExtrinsic(0),
-                    event: Event::governance(RawEvent::NewProposal(public, hash2))
+                    event: Event::governance(RawEvent::DepositReserved(hash2, public, 1))
                 },]
             );
         });
     }
 
     #[test]
-    fn propose_duplicate_should_fail() {
+    fn propose_duplicate_should_get_new_index() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
             let public = get_test_key();
             let (title, proposal) = generate_proposal();
             let category = governance::ProposalCategory::Signaling;
+            assert_ok!(propose(public, title, proposal, category.clone()));
             assert_ok!(propose(public, title, proposal, category));
-            assert_eq!(propose(public, title, proposal, category), Err("Proposal already exists"));
+            assert_eq!(Governance::proposal_hash_of(0), Some(build_proposal_hash(public, proposal, 0)));
+            assert_eq!(Governance::proposal_hash_of(1), Some(build_proposal_hash(public, proposal, 1)));
+            assert_eq!(Governance::proposal_of(build_proposal_hash(public, proposal, 1)).unwrap().index, 1);
+            assert_eq!(Governance::proposal_hash_of(2), None);
         });
     }
 
@@ -193,8 +377,9 @@ mod tests {
             let public = get_test_key();
             let (title, _) = generate_proposal();
             let proposal = vec![];
-            let category = governance::ProposalCategory::Upgrade;
-            assert_eq!(propose(public, title, &proposal, category), Err("Proposal must not be empty"));
+            let category = governance::ProposalCategory::Upgrade(H256::zero());
+            assert_eq!(Governance::note_preimage(Origin::signed(public), proposal.clone()), Err("Preimage must not be empty"));
+            assert_eq!(propose(public, title, &proposal, category), Err("Proposal contents have not been noted"));
         });
     }
 
@@ -205,7 +390,7 @@ mod tests {
             let public = get_test_key();
             let (_, proposal) = generate_proposal();
             let title = vec![];
-            let category = governance::ProposalCategory::Upgrade;
+            let category = governance::ProposalCategory::Upgrade(H256::zero());
             assert_eq!(propose(public, &title, proposal, category), Err("Proposal must have title"));
         });
     }
@@ -216,16 +401,26 @@ mod tests {
             System::set_block_number(1);
             let public = get_test_key();
             let (title, proposal) = generate_proposal();
-            let category = governance::ProposalCategory::Upgrade;
+            let category = governance::ProposalCategory::Signaling;
             assert_ok!(propose(public, title, proposal, category));
-            let hash = build_proposal_hash(public, &proposal);
+            let hash = build_proposal_hash(public, &proposal, 0);
 
             // create a comment
             let comment: &[u8] = b"pls do not do this";
             assert_ok!(add_comment(public, hash, comment));
-            assert_eq!(System::events()[1], EventRecord {
+            assert_eq!(Governance::comment_count(hash), 1);
+            assert_eq!(Governance::comment_of((hash, 0)), Some(governance::CommentRecord {
+                author: public,
+                parent: None,
+                body: comment.to_vec(),
+                posted_at: 1,
+                edit_count: 0,
+                last_edited_at: None,
+                deleted: false,
+            }));
+            assert_eq!(System::events()[3], EventRecord {
                 phase: Phase::ApplyExtrinsic(0),
-                event: Event::governance(RawEvent::NewComment(public, hash))
+                event: Event::governance(RawEvent::NewComment(public, hash, 0))
             });
         });
     }
@@ -248,17 +443,26 @@ mod tests {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
             let public = get_test_key();
-            let category = governance::ProposalCategory::Funding(12);
+            let category = governance::ProposalCategory::Funding(12, get_other_key());
             let (title, proposal) = generate_proposal();
-            let hash = build_proposal_hash(public, &proposal);
+            let hash = build_proposal_hash(public, &proposal, 0);
             assert_ok!(propose(public, title, proposal, category));
             assert_ok!(advance_proposal(public, hash));
-            assert_ok!(advance_proposal(public, hash));
+            assert_eq!(advance_proposal(public, hash), Err("Voting period has not ended"));
+            finish_voting(hash);
             assert_eq!(advance_proposal(public, hash), Err("Proposal already completed"));
             assert_eq!(System::events(), vec![
                 EventRecord {
                     phase: Phase::ApplyExtrinsic(0),
-                    event: Event::governance(RawEvent::NewProposal(public, hash))
+                    event: Event::governance(RawEvent::PreimageNoted(Blake2Hasher::hash(proposal), public))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::NewProposal(public, 0, hash))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::DepositReserved(hash, public, 1))
                 },
                 EventRecord {
                     phase: Phase::ApplyExtrinsic(0),
@@ -266,7 +470,11 @@ mod tests {
                 },
                 EventRecord {
                     phase: Phase::ApplyExtrinsic(0),
-                    event: Event::governance(RawEvent::VotingCompleted(hash))
+                    event: Event::governance(RawEvent::VotingCompleted(hash, governance::ProposalOutcome::NoQuorum, vec![0, 0]))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::DepositReturned(hash, public, 1))
                 },]
             );
         });
@@ -277,9 +485,9 @@ mod tests {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
             let public = get_test_key();
-            let category = governance::ProposalCategory::Funding(12);
+            let category = governance::ProposalCategory::Funding(12, get_other_key());
             let (title, proposal) = generate_proposal();
-            let hash = build_proposal_hash(public, &proposal);
+            let hash = build_proposal_hash(public, &proposal, 0);
 
             let other_pair: Pair = Pair::from_seed(&hex!("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f61"));
             let other_public: H256 = other_pair.public().0.into();
@@ -293,16 +501,24 @@ mod tests {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
             let public = get_test_key();
-            let category = governance::ProposalCategory::Funding(12);
+            let category = governance::ProposalCategory::Funding(12, get_other_key());
             let (title, proposal) = generate_proposal();
-            let hash = build_proposal_hash(public, &proposal);
+            let hash = build_proposal_hash(public, &proposal, 0);
             assert_ok!(propose(public, title, proposal, category));
             assert_ok!(advance_proposal(public, hash));
             assert_ok!(submit_vote(public, hash, true));
             assert_eq!(System::events(), vec![
                 EventRecord {
                     phase: Phase::ApplyExtrinsic(0),
-                    event: Event::governance(RawEvent::NewProposal(public, hash))
+                    event: Event::governance(RawEvent::PreimageNoted(Blake2Hasher::hash(proposal), public))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::NewProposal(public, 0, hash))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::DepositReserved(hash, public, 1))
                 },
                 EventRecord {
                     phase: Phase::ApplyExtrinsic(0),
@@ -310,7 +526,7 @@ mod tests {
                 },
                 EventRecord {
                     phase: Phase::ApplyExtrinsic(0),
-                    event: Event::governance(RawEvent::VoteSubmitted(hash, public, true))
+                    event: Event::governance(RawEvent::VoteSubmitted(hash, public, vote(true)))
                 },]
             );
         });
@@ -321,14 +537,1503 @@ mod tests {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
             let public = get_test_key();
-            let category = governance::ProposalCategory::Funding(12);
+            let category = governance::ProposalCategory::Funding(12, get_other_key());
             let (title, proposal) = generate_proposal();
-            let hash = build_proposal_hash(public, &proposal);
+            let hash = build_proposal_hash(public, &proposal, 0);
             assert_ok!(propose(public, title, proposal, category));
             assert_eq!(submit_vote(public, hash, true), Err("Proposal not in voting stage"));
             assert_ok!(advance_proposal(public, hash));
-            assert_ok!(advance_proposal(public, hash));
+            finish_voting(hash);
             assert_eq!(submit_vote(public, hash, true), Err("Proposal not in voting stage"));
         });
     }
-} 
+
+    #[test]
+    fn completed_proposal_should_record_tally() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let category = governance::ProposalCategory::Signaling;
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            assert_ok!(submit_vote(other_public, hash, true));
+            assert_eq!(Governance::tally(hash), vec![29, 0]);
+            finish_voting(hash);
+            assert_eq!(Governance::proposal_of(hash).unwrap().outcome, Some(governance::ProposalOutcome::Passed));
+            assert_eq!(System::events()[6], EventRecord {
+                phase: Phase::ApplyExtrinsic(0),
+                event: Event::governance(RawEvent::VotingCompleted(hash, governance::ProposalOutcome::Passed, vec![29, 0]))
+            });
+        });
+    }
+
+    #[test]
+    fn votes_should_be_weighted_by_stake() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let category = governance::ProposalCategory::Signaling;
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            assert_ok!(submit_vote(other_public, hash, false));
+            assert_eq!(Governance::vote_of((hash, public)), Some(vote(true)));
+            assert_eq!(Governance::vote_of((hash, other_public)), Some(vote(false)));
+            assert_eq!(Governance::tally(hash), vec![9, 20]);
+            finish_voting(hash);
+            assert_eq!(Governance::proposal_of(hash).unwrap().outcome, Some(governance::ProposalOutcome::Rejected));
+        });
+    }
+
+    #[test]
+    fn moved_stake_should_be_counted_once() {
+        use runtime_support::StorageMap;
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let third_public = H256::from(3);
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(other_public, hash, true));
+
+            // move the voter's balance to a fresh account, which votes again
+            <balances::FreeBalance<Test>>::insert(other_public, 0);
+            <balances::FreeBalance<Test>>::insert(third_public, 20);
+            assert_ok!(submit_vote(third_public, hash, true));
+            assert_eq!(Governance::tally(hash), vec![20, 0]);
+        });
+    }
+
+    #[test]
+    fn tied_vote_should_be_rejected() {
+        use runtime_support::StorageMap;
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            // match the proposer's balance after their deposit is reserved
+            <balances::FreeBalance<Test>>::insert(other_public, 9);
+
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            assert_ok!(submit_vote(other_public, hash, false));
+            assert_eq!(Governance::tally(hash), vec![9, 9]);
+            finish_voting(hash);
+            assert_eq!(Governance::proposal_of(hash).unwrap().outcome, Some(governance::ProposalOutcome::Rejected));
+        });
+    }
+
+    #[test]
+    fn proposal_should_advance_automatically() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let category = governance::ProposalCategory::Signaling;
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            let record = Governance::proposal_of(hash).unwrap();
+            assert_eq!(record.voting_starts_at, 11);
+            assert_eq!(record.voting_ends_at, 21);
+
+            System::set_block_number(10);
+            assert_ok!(Governance::end_block(10));
+            assert_eq!(Governance::proposal_of(hash).unwrap().stage, governance::ProposalStage::PreVoting);
+
+            System::set_block_number(11);
+            assert_ok!(Governance::end_block(11));
+            assert_eq!(Governance::proposal_of(hash).unwrap().stage, governance::ProposalStage::Voting);
+            assert_ok!(submit_vote(public, hash, true));
+
+            System::set_block_number(21);
+            assert_ok!(Governance::end_block(21));
+            assert_eq!(Governance::proposal_of(hash).unwrap().stage, governance::ProposalStage::Completed);
+            assert_eq!(Governance::active_proposals(), vec![]);
+            assert_eq!(System::events(), vec![
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::PreimageNoted(Blake2Hasher::hash(proposal), public))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::NewProposal(public, 0, hash))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::DepositReserved(hash, public, 1))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::VotingStarted(hash))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::VoteSubmitted(hash, public, vote(true)))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::VotingCompleted(hash, governance::ProposalOutcome::Passed, vec![9, 0]))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::DepositReturned(hash, public, 1))
+                },]
+            );
+        });
+    }
+
+    #[test]
+    fn missing_active_proposal_should_not_stall_others() {
+        use runtime_support::StorageValue;
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            <governance::ActiveProposals<Test>>::put(vec![(H256::zero(), 11), (hash, 11)]);
+
+            System::set_block_number(11);
+            assert_ok!(Governance::end_block(11));
+            assert_eq!(Governance::proposal_of(hash).unwrap().stage, governance::ProposalStage::Voting);
+            assert_eq!(Governance::active_proposals(), vec![(hash, 21)]);
+        });
+    }
+
+    #[test]
+    fn advance_proposal_should_restart_voting_period() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let category = governance::ProposalCategory::Signaling;
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            System::set_block_number(5);
+            assert_ok!(advance_proposal(public, hash));
+            let record = Governance::proposal_of(hash).unwrap();
+            assert_eq!(record.voting_starts_at, 5);
+            assert_eq!(record.voting_ends_at, 15);
+            assert_eq!(Governance::active_proposals(), vec![(hash, 15)]);
+        });
+    }
+
+    #[test]
+    fn advance_proposal_should_wait_for_minimum_pre_voting_period() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_eq!(Governance::advance_proposal(Origin::signed(public), index_of(hash)),
+                       Err("Minimum pre-voting period has not passed"));
+            System::set_block_number(2);
+            assert_eq!(Governance::advance_proposal(Origin::signed(public), index_of(hash)),
+                       Err("Minimum pre-voting period has not passed"));
+            assert_eq!(Governance::proposal_of(hash).unwrap().stage, governance::ProposalStage::PreVoting);
+            System::set_block_number(3);
+            assert_ok!(Governance::advance_proposal(Origin::signed(public), index_of(hash)));
+            assert_eq!(Governance::proposal_of(hash).unwrap().voting_ends_at, 13);
+        });
+    }
+
+    #[test]
+    fn propose_should_reserve_deposit() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let category = governance::ProposalCategory::Signaling;
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert_eq!(Balances::free_balance(&public), 9);
+            assert_eq!(Balances::reserved_balance(&public), 1);
+            assert_eq!(Governance::proposal_of(hash).unwrap().deposit, 1);
+
+            finish_voting(hash);
+            assert_eq!(Balances::free_balance(&public), 10);
+            assert_eq!(Balances::reserved_balance(&public), 0);
+        });
+    }
+
+    #[test]
+    fn propose_without_deposit_should_fail() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let poor = H256::zero();
+            let category = governance::ProposalCategory::Signaling;
+            let (title, proposal) = generate_proposal();
+            assert_eq!(propose(poor, title, proposal, category), Err("Proposer's balance too low"));
+            assert_eq!(Governance::proposal_count(), 0);
+        });
+    }
+
+    #[test]
+    fn cancel_proposal_should_slash_deposit() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let category = governance::ProposalCategory::Signaling;
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert!(Governance::cancel_proposal(Origin::signed(public), index_of(hash)).is_err());
+            assert_ok!(Governance::cancel_proposal(Origin::ROOT, index_of(hash)));
+            assert_eq!(Governance::proposal_of(hash).unwrap().stage, governance::ProposalStage::Cancelled);
+            assert_eq!(Governance::active_proposals(), vec![]);
+            assert_eq!(Balances::free_balance(&public), 9);
+            assert_eq!(Balances::reserved_balance(&public), 0);
+            assert_eq!(Governance::pot(), 101);
+            assert_eq!(Balances::free_balance(&get_treasury_key()), 101);
+            assert_eq!(Balances::total_issuance(), 130);
+            assert_eq!(advance_proposal(public, hash), Err("Proposal was cancelled"));
+            assert_eq!(Governance::cancel_proposal(Origin::ROOT, index_of(hash)), Err("Proposal was cancelled"));
+            assert_eq!(System::events()[3..].to_vec(), vec![
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::ProposalCancelled(hash))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::DepositSlashed(hash, public, 1))
+                },]
+            );
+        });
+    }
+
+    #[test]
+    fn edit_proposal_should_keep_revisions() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            System::set_block_number(2);
+            assert_eq!(Governance::edit_proposal(Origin::signed(other_public), index_of(hash), b"Title".to_vec(), note_contents(other_public, b"Contents")),
+                       Err("Proposal must be edited by author"));
+            assert_eq!(Governance::edit_proposal(Origin::signed(public), index_of(hash), b"Title".to_vec(),
+                                                 governance::ProposalContents::Preimage(Blake2Hasher::hash(b"Unnoted"))),
+                       Err("Proposal contents have not been noted"));
+            assert_ok!(Governance::edit_proposal(Origin::signed(public), index_of(hash), b"Title".to_vec(),
+                                                 governance::ProposalContents::Preimage(Blake2Hasher::hash(b"Contents"))));
+            assert_eq!(System::events().last().unwrap().event, Event::governance(RawEvent::ProposalEdited(hash, 1)));
+
+            let record = Governance::proposal_of(hash).unwrap();
+            assert_eq!(record.title, b"Title".to_vec());
+            assert_eq!(record.contents, governance::ProposalContents::Preimage(Blake2Hasher::hash(b"Contents")));
+            assert_eq!(record.revision, 1);
+            assert_eq!(Governance::revision_of((hash, 0)), Some(governance::ProposalRevision {
+                revision: 0,
+                revised_at: 1,
+                title: title.to_vec(),
+                contents: governance::ProposalContents::Preimage(Blake2Hasher::hash(proposal)),
+            }));
+            assert_eq!(Governance::revision_of((hash, 1)).unwrap().revised_at, 2);
+
+            assert_ok!(advance_proposal(public, hash));
+            assert_eq!(Governance::edit_proposal(Origin::signed(public), index_of(hash), b"Title".to_vec(),
+                                                 governance::ProposalContents::Preimage(Blake2Hasher::hash(b"Contents"))),
+                       Err("Proposal can only be edited before voting"));
+        });
+    }
+
+    #[test]
+    fn withdraw_proposal_should_return_deposit() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_eq!(Governance::withdraw_proposal(Origin::signed(other_public), index_of(hash)), Err("Proposal must be withdrawn by author"));
+            assert_ok!(Governance::withdraw_proposal(Origin::signed(public), index_of(hash)));
+            assert_eq!(Governance::proposal_of(hash).unwrap().stage, governance::ProposalStage::Withdrawn);
+            assert_eq!(Governance::active_proposals(), vec![]);
+            assert_eq!(Balances::free_balance(&public), 10);
+            assert_eq!(Balances::reserved_balance(&public), 0);
+            assert_eq!(System::events()[3..].to_vec(), vec![
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::ProposalWithdrawn(hash))
+                },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::governance(RawEvent::DepositReturned(hash, public, 1))
+                }]
+            );
+            assert_eq!(advance_proposal(public, hash), Err("Proposal was withdrawn"));
+            assert_eq!(Governance::cancel_proposal(Origin::ROOT, index_of(hash)), Err("Proposal was withdrawn"));
+        });
+    }
+
+    #[test]
+    fn withdraw_proposal_should_fail_once_voting() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            assert_eq!(Governance::withdraw_proposal(Origin::signed(public), index_of(hash)), Err("Proposal can only be withdrawn before voting"));
+            assert_ok!(Governance::cancel_proposal(Origin::ROOT, index_of(hash)));
+            assert_eq!(Governance::proposal_of(hash).unwrap().stage, governance::ProposalStage::Cancelled);
+        });
+    }
+
+    #[test]
+    fn passed_funding_proposal_should_pay_beneficiary() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let category = governance::ProposalCategory::Funding(30, other_public);
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            assert_ok!(submit_vote(other_public, hash, true));
+            finish_voting(hash);
+            assert_eq!(Balances::free_balance(&other_public), 50);
+            assert_eq!(Governance::pot(), 70);
+            assert_eq!(Balances::free_balance(&get_treasury_key()), 70);
+            assert_eq!(Balances::total_issuance(), 130);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::FundingPaid(hash, other_public, 30)));
+        });
+    }
+
+    #[test]
+    fn funding_proposal_exceeding_pot_should_not_pay() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let category = governance::ProposalCategory::Funding(101, other_public);
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            assert_ok!(submit_vote(other_public, hash, true));
+            finish_voting(hash);
+            assert_eq!(Governance::proposal_of(hash).unwrap().outcome, Some(governance::ProposalOutcome::Passed));
+            assert_eq!(Balances::free_balance(&other_public), 20);
+            assert_eq!(Governance::pot(), 100);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::FundingFailed(hash)));
+        });
+    }
+
+    #[test]
+    fn funding_proposal_should_not_leave_dust() {
+        with_externalities(&mut new_test_ext_with_fees(5, 0), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let new_account = H256::from(7);
+            let fund = |proposal: &[u8], index: u32, amount: u64, beneficiary: H256| {
+                let hash = build_proposal_hash(public, &proposal, index);
+                assert_ok!(propose(public, proposal, proposal, governance::ProposalCategory::Funding(amount, beneficiary)));
+                assert_ok!(advance_proposal(public, hash));
+                assert_ok!(submit_vote(public, hash, true));
+                assert_ok!(submit_vote(other_public, hash, true));
+                finish_voting(hash);
+                assert_eq!(Governance::proposal_of(hash).unwrap().outcome, Some(governance::ProposalOutcome::Passed));
+                (hash, System::events().last().unwrap().event.clone())
+            };
+
+            // the treasury would be left with less than the existential deposit
+            let (hash, event) = fund(b"Nearly empty the treasury", 0, 96, other_public);
+            assert_eq!(event, Event::governance(RawEvent::FundingFailed(hash)));
+            assert_eq!(Governance::pot(), 100);
+
+            // a new account would be created with less than the existential deposit
+            let (hash, event) = fund(b"Pay dust to a new account", 1, 3, new_account);
+            assert_eq!(event, Event::governance(RawEvent::FundingFailed(hash)));
+            assert_eq!(Balances::free_balance(&new_account), 0);
+
+            let (hash, event) = fund(b"Pay a new account", 2, 5, new_account);
+            assert_eq!(event, Event::governance(RawEvent::FundingPaid(hash, new_account, 5)));
+            assert_eq!(Balances::free_balance(&new_account), 5);
+            assert_eq!(Governance::pot(), 95);
+            assert_eq!(Balances::total_issuance(), 130);
+        });
+    }
+
+    #[test]
+    fn propose_zero_funding_should_fail() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let category = governance::ProposalCategory::Funding(0, get_other_key());
+            let (title, proposal) = generate_proposal();
+            assert_eq!(propose(public, title, proposal, category), Err("Funding amount must not be zero"));
+        });
+    }
+
+    #[test]
+    fn note_preimage_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let code: &[u8] = b"new runtime code";
+            let code_hash = Blake2Hasher::hash(code);
+            assert_ok!(Governance::note_preimage(Origin::signed(public), code.to_vec()));
+            assert_eq!(Governance::preimage_of(code_hash), Some((code.to_vec(), public, 0)));
+            assert_eq!(Governance::note_preimage(Origin::signed(public), code.to_vec()), Err("Preimage already noted"));
+            assert_eq!(Governance::note_preimage(Origin::signed(public), vec![0; governance::MAX_PREIMAGE_LEN + 1]),
+                       Err("Preimage is too long"));
+        });
+    }
+
+    #[test]
+    fn reap_preimage_should_return_deposit_once_unused() {
+        use runtime_support::StorageValue;
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            <governance::PreimageByteDeposit<Test>>::put(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let contents: &[u8] = b"Contents";
+            let contents_hash = Blake2Hasher::hash(contents);
+            assert_ok!(Governance::note_preimage(Origin::signed(public), contents.to_vec()));
+            assert_eq!(Balances::reserved_balance(&public), 8);
+            assert_eq!(Governance::note_preimage(Origin::signed(other_public), vec![0; 21]), Err("Balance too low to note preimage"));
+
+            let hash = build_proposal_hash(public, contents, 0);
+            assert_ok!(propose(public, b"Title", contents, governance::ProposalCategory::Signaling));
+            assert_eq!(Governance::preimage_users(contents_hash), 1);
+            assert_eq!(Governance::reap_preimage(Origin::signed(other_public), contents_hash),
+                       Err("Preimage must be reaped by the account that noted it"));
+            assert_eq!(Governance::reap_preimage(Origin::signed(public), contents_hash), Err("Preimage is still in use"));
+
+            assert_ok!(advance_proposal(public, hash));
+            finish_voting(hash);
+            assert_eq!(Governance::preimage_users(contents_hash), 0);
+            assert_ok!(Governance::reap_preimage(Origin::signed(public), contents_hash));
+            assert_eq!(Governance::preimage_of(contents_hash), None);
+            assert_eq!(Balances::reserved_balance(&public), 0);
+            assert_eq!(Balances::free_balance(&public), 10);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::PreimageReaped(contents_hash, public)));
+        });
+    }
+
+    #[test]
+    fn propose_with_off_chain_contents_should_validate_format() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let create = |contents| Governance::create_proposal(Origin::signed(public), b"Title".to_vec(), contents,
+                                                               governance::ProposalCategory::Signaling, vec![],
+                                                               governance::VotingScheme::SingleChoice, false);
+            let doc_hash = Blake2Hasher::hash(b"Document");
+
+            assert_eq!(create(governance::ProposalContents::Inline(vec![])), Err("Proposal must not be empty"));
+            assert_eq!(create(governance::ProposalContents::Inline(vec![0; governance::MAX_INLINE_CONTENTS_LEN + 1])),
+                       Err("Inline contents are too long"));
+            assert_eq!(create(governance::ProposalContents::Uri(doc_hash, b"example.com/doc".to_vec())),
+                       Err("Invalid contents URI"));
+            assert_eq!(create(governance::ProposalContents::Uri(doc_hash, b"https://example.com/a doc".to_vec())),
+                       Err("Invalid contents URI"));
+            assert_eq!(create(governance::ProposalContents::Ipfs(b"QmNotACid".to_vec())), Err("Invalid IPFS CID"));
+            assert_eq!(create(governance::ProposalContents::Ipfs(b"bafyBEIG".to_vec())), Err("Invalid IPFS CID"));
+            assert_eq!(create(governance::ProposalContents::Ipfs(b"ba".to_vec())), Err("Invalid IPFS CID"));
+            // one character short of a CIDv1 with a 256-bit digest
+            assert_eq!(create(governance::ProposalContents::Ipfs(
+                b"bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzd".to_vec())), Err("Invalid IPFS CID"));
+
+            assert_ok!(create(governance::ProposalContents::Inline(b"Contents".to_vec())));
+            assert_ok!(create(governance::ProposalContents::Uri(doc_hash, b"https://example.com/doc".to_vec())));
+            assert_ok!(create(governance::ProposalContents::Ipfs(b"QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG".to_vec())));
+            assert_ok!(create(governance::ProposalContents::Ipfs(
+                b"bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_vec())));
+            assert_eq!(Governance::proposal_count(), 4);
+            assert_eq!(Governance::preimage_users(doc_hash), 0);
+        });
+    }
+
+    #[test]
+    fn propose_upgrade_without_preimage_should_fail() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let code_hash = Blake2Hasher::hash(b"new runtime code");
+            let category = governance::ProposalCategory::Upgrade(code_hash);
+            let (title, proposal) = generate_proposal();
+            assert_eq!(propose(public, title, proposal, category), Err("Upgrade code has not been noted"));
+        });
+    }
+
+    #[test]
+    fn passed_upgrade_proposal_should_set_code() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let code: &[u8] = b"new runtime code";
+            let code_hash = Blake2Hasher::hash(code);
+            assert_ok!(Governance::note_preimage(Origin::signed(public), code.to_vec()));
+            let category = governance::ProposalCategory::Upgrade(code_hash);
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            assert_ok!(submit_vote(get_other_key(), hash, true));
+            finish_voting(hash);
+            assert_eq!(runtime_io::storage(b":code"), Some(code.to_vec()));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::RuntimeUpgraded(hash, code_hash)));
+        });
+    }
+
+    #[test]
+    fn fast_track_should_shorten_upgrade_voting() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let code: &[u8] = b"new runtime code";
+            assert_ok!(Governance::note_preimage(Origin::signed(public), code.to_vec()));
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Upgrade(Blake2Hasher::hash(code))));
+            assert_eq!(Governance::fast_track(Origin::signed(public), index_of(hash)), Err("Origin is not the technical committee"));
+            assert_ok!(Governance::fast_track(Origin::signed(other_public), index_of(hash)));
+            let record = Governance::proposal_of(hash).unwrap();
+            assert_eq!(record.stage, governance::ProposalStage::Voting);
+            assert_eq!(record.voting_ends_at, 4);
+            assert_eq!(Governance::active_proposals(), vec![(hash, 4)]);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::ProposalFastTracked(hash)));
+            assert_eq!(Governance::fast_track(Origin::signed(other_public), index_of(hash)), Err("Proposal not in pre-voting stage"));
+
+            let signaling = b"Signaling proposal";
+            let signaling_hash = build_proposal_hash(public, signaling, 1);
+            assert_ok!(propose(public, title, signaling, governance::ProposalCategory::Signaling));
+            assert_eq!(Governance::fast_track(Origin::signed(other_public), index_of(signaling_hash)),
+                       Err("Only upgrade proposals may be fast-tracked"));
+        });
+    }
+
+    #[test]
+    fn veto_should_stop_proposal_and_cool_off() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            assert!(Governance::veto(Origin::signed(other_public), index_of(hash)).is_err());
+            assert_ok!(Governance::veto(Origin::ROOT, index_of(hash)));
+            assert_eq!(Governance::proposal_of(hash).unwrap().stage, governance::ProposalStage::Vetoed);
+            assert_eq!(Governance::active_proposals(), vec![]);
+            assert_eq!(Balances::free_balance(&public), 10);
+            // vetoed at block 3, once voting opened
+            assert_eq!(Governance::vetoed_until(Blake2Hasher::hash(proposal)), Some(23));
+            assert_eq!(System::events()[4].event, Event::governance(RawEvent::ProposalVetoed(hash, 23)));
+            assert_eq!(Governance::veto(Origin::ROOT, index_of(hash)), Err("Proposal was vetoed"));
+            assert_eq!(submit_vote(public, hash, true), Err("Proposal not in voting stage"));
+
+            assert_eq!(propose(other_public, title, proposal, governance::ProposalCategory::Signaling),
+                       Err("Proposal is cooling off after a veto"));
+            System::set_block_number(23);
+            assert_ok!(propose(other_public, title, proposal, governance::ProposalCategory::Signaling));
+        });
+    }
+
+    #[test]
+    fn edit_should_not_bring_back_vetoed_contents() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(Governance::veto(Origin::ROOT, index_of(hash)));
+
+            let (title2, proposal2): (&[u8], &[u8]) = (b"Another title", b"Other contents");
+            let hash2 = build_proposal_hash(other_public, &proposal2, 1);
+            assert_ok!(propose(other_public, title2, proposal2, governance::ProposalCategory::Signaling));
+            assert_eq!(Governance::edit_proposal(Origin::signed(other_public), index_of(hash2), title.to_vec(),
+                                                 note_contents(other_public, proposal)),
+                       Err("Proposal is cooling off after a veto"));
+            assert_eq!(Governance::proposal_of(hash2).unwrap().revision, 0);
+
+            System::set_block_number(21);
+            assert_ok!(Governance::edit_proposal(Origin::signed(other_public), index_of(hash2), title.to_vec(),
+                                                 note_contents(other_public, proposal)));
+        });
+    }
+
+    #[test]
+    fn passed_call_proposal_should_dispatch() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let (title, proposal) = generate_proposal();
+            let target_hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+
+            // propose that root cancels the first proposal
+            let call = Call::Governance(governance::Call::cancel_proposal(0));
+            let category = governance::ProposalCategory::Call(call.encode());
+            let (title2, proposal2): (&[u8], &[u8]) = (b"Cancel it", b"Cancel the first proposal");
+            let hash = build_proposal_hash(public, &proposal2, 1);
+            assert_ok!(propose(public, title2, proposal2, category));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            finish_voting(hash);
+            assert_eq!(Governance::proposal_of(target_hash).unwrap().stage, governance::ProposalStage::Cancelled);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::CallDispatched(hash, true)));
+        });
+    }
+
+    #[test]
+    fn failed_call_proposal_should_report_failure() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let call = Call::Governance(governance::Call::cancel_proposal(99));
+            let category = governance::ProposalCategory::Call(call.encode());
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            finish_voting(hash);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::CallDispatched(hash, false)));
+        });
+    }
+
+    #[test]
+    fn propose_undecodable_call_should_fail() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let category = governance::ProposalCategory::Call(vec![0xff, 0xff]);
+            let (title, proposal) = generate_proposal();
+            assert_eq!(propose(public, title, proposal, category), Err("Call could not be decoded"));
+        });
+    }
+
+    #[test]
+    fn submit_vote_twice_should_fail() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let category = governance::ProposalCategory::Signaling;
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            assert_eq!(submit_vote(public, hash, false), Err("Vote already submitted"));
+        });
+    }
+
+    #[test]
+    fn change_vote_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let category = governance::ProposalCategory::Signaling;
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert_ok!(advance_proposal(public, hash));
+            assert_eq!(change_vote(other_public, hash, false), Err("Vote has not been submitted"));
+            assert_ok!(submit_vote(other_public, hash, true));
+            assert_ok!(change_vote(other_public, hash, false));
+            assert_eq!(Governance::proposal_voters(hash), vec![other_public]);
+            assert_eq!(Governance::tally(hash), vec![0, 20]);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::VoteChanged(hash, other_public, vote(false))));
+        });
+    }
+
+    #[test]
+    fn retract_vote_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let category = governance::ProposalCategory::Signaling;
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            assert_ok!(submit_vote(other_public, hash, false));
+            assert_ok!(retract_vote(other_public, hash));
+            assert_eq!(Governance::vote_of((hash, other_public)), None);
+            assert_eq!(Governance::proposal_voters(hash), vec![public]);
+            assert_eq!(Governance::tally(hash), vec![9, 0]);
+            assert_eq!(retract_vote(other_public, hash), Err("Vote has not been submitted"));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::VoteRetracted(hash, other_public)));
+
+            // a retracted vote can be submitted again
+            assert_ok!(submit_vote(other_public, hash, true));
+            assert_eq!(Governance::tally(hash), vec![29, 0]);
+        });
+    }
+
+    #[test]
+    fn comments_should_be_numbered_per_proposal() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            let (title2, proposal2): (&[u8], &[u8]) = (b"Proposal 2", b"Proposal 2");
+            let hash2 = build_proposal_hash(public, &proposal2, 1);
+            assert_ok!(propose(public, title2, proposal2, governance::ProposalCategory::Signaling));
+
+            assert_ok!(add_comment(public, hash, b"first"));
+            System::set_block_number(2);
+            assert_ok!(add_comment(other_public, hash, b"second"));
+            assert_ok!(add_comment(other_public, hash2, b"elsewhere"));
+            assert_eq!(Governance::comment_count(hash), 2);
+            assert_eq!(Governance::comment_count(hash2), 1);
+            let comment = Governance::comment_of((hash, 1)).unwrap();
+            assert_eq!(comment.author, other_public);
+            assert_eq!(comment.body, b"second".to_vec());
+            assert_eq!(comment.posted_at, 2);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::NewComment(other_public, hash2, 0)));
+        });
+    }
+
+    #[test]
+    fn reply_to_comment_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_eq!(reply_to_comment(other_public, hash, b"orphan", 0), Err("Parent comment does not exist"));
+            assert_ok!(add_comment(public, hash, b"thoughts?"));
+            assert_ok!(reply_to_comment(other_public, hash, b"no", 0));
+            assert_eq!(Governance::comment_of((hash, 1)).unwrap().parent, Some(0));
+        });
+    }
+
+    #[test]
+    fn edit_comment_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(add_comment(public, hash, b"tpyo"));
+            assert_eq!(edit_comment(other_public, hash, 0, b"hijacked"), Err("Comment must be edited by author"));
+            assert_eq!(edit_comment(public, hash, 1, b"typo"), Err("Comment does not exist"));
+
+            System::set_block_number(3);
+            assert_ok!(edit_comment(public, hash, 0, b"typo"));
+            let comment = Governance::comment_of((hash, 0)).unwrap();
+            assert_eq!(comment.body, b"typo".to_vec());
+            assert_eq!(comment.edit_count, 1);
+            assert_eq!(comment.last_edited_at, Some(3));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::CommentEdited(public, hash, 0)));
+        });
+    }
+
+    #[test]
+    fn delete_comment_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(add_comment(public, hash, b"regrettable"));
+            assert_ok!(reply_to_comment(other_public, hash, b"indeed", 0));
+            assert_eq!(delete_comment(other_public, hash, 0), Err("Comment must be deleted by author"));
+            assert_ok!(delete_comment(public, hash, 0));
+            let comment = Governance::comment_of((hash, 0)).unwrap();
+            assert!(comment.deleted);
+            assert_eq!(comment.body, vec![]);
+            assert_eq!(Governance::comment_of((hash, 1)).unwrap().parent, Some(0));
+            assert_eq!(delete_comment(public, hash, 0), Err("Comment was deleted"));
+            assert_eq!(edit_comment(public, hash, 0, b"undo"), Err("Comment was deleted"));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::CommentDeleted(public, hash, 0)));
+        });
+    }
+
+    #[test]
+    fn proposal_below_category_turnout_should_have_no_quorum() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let code: &[u8] = b"new runtime code";
+            assert_ok!(Governance::note_preimage(Origin::signed(public), code.to_vec()));
+            let category = governance::ProposalCategory::Upgrade(Blake2Hasher::hash(code));
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, category));
+            assert_ok!(advance_proposal(public, hash));
+            // 9 of the 30 held outside the treasury is below the 40% turnout
+            // an upgrade needs
+            assert_ok!(submit_vote(public, hash, true));
+            finish_voting(hash);
+            assert_eq!(Governance::proposal_of(hash).unwrap().outcome, Some(governance::ProposalOutcome::NoQuorum));
+            assert_eq!(runtime_io::storage(b":code"), None);
+        });
+    }
+
+    #[test]
+    fn proposal_below_category_approval_should_be_rejected() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let rules = governance::CategoryRules {
+                min_turnout: Permill::from_percent(0),
+                approval: Permill::from_percent(75),
+                threshold: vote_threshold::VoteThreshold::SimpleMajority,
+            };
+            assert!(Governance::set_category_rules(Origin::signed(public), governance::ProposalKind::Signaling, rules).is_err());
+            assert_ok!(Governance::set_category_rules(Origin::ROOT, governance::ProposalKind::Signaling, rules));
+            assert_eq!(Governance::category_rules(governance::ProposalKind::Signaling), Some(rules));
+
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            // 20 of 29 is a majority, but short of the 75% required
+            assert_ok!(submit_vote(public, hash, false));
+            assert_ok!(submit_vote(other_public, hash, true));
+            finish_voting(hash);
+            assert_eq!(Governance::proposal_of(hash).unwrap().outcome, Some(governance::ProposalOutcome::Rejected));
+        });
+    }
+
+    #[test]
+    fn unconfigured_call_rules_should_fail_safe() {
+        use runtime_support::StorageMap;
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            <governance::CategoryRulesOf<Test>>::remove(governance::ProposalKind::Call);
+            assert_eq!(Governance::rules_for(governance::ProposalKind::Call).threshold,
+                       vote_threshold::VoteThreshold::SuperMajorityApprove);
+            assert_eq!(Governance::rules_for(governance::ProposalKind::Signaling).threshold,
+                       vote_threshold::VoteThreshold::SimpleMajority);
+            // Funding has no rules in the mock runtime either
+            assert_eq!(Governance::category_rules(governance::ProposalKind::Funding), None);
+            assert_eq!(Governance::rules_for(governance::ProposalKind::Funding).threshold,
+                       vote_threshold::VoteThreshold::SuperMajorityApprove);
+
+            let call = Call::Governance(governance::Call::cancel_proposal(0));
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Call(call.encode())));
+            assert_ok!(advance_proposal(public, hash));
+            // a lone vote falls far short of the turnout a root call needs
+            assert_ok!(submit_vote(public, hash, true));
+            finish_voting(hash);
+            assert_eq!(Governance::proposal_of(hash).unwrap().outcome, Some(governance::ProposalOutcome::NoQuorum));
+        });
+    }
+
+    #[test]
+    fn vote_thresholds_should_bias_by_turnout() {
+        use vote_threshold::{VoteThreshold, Approved};
+        assert_eq!(VoteThreshold::SuperMajorityApprove.approved(60, 50, 110, 210), false);
+        assert_eq!(VoteThreshold::SuperMajorityApprove.approved(100, 50, 150, 210), true);
+        // the same weighted votes need a larger majority from less stake
+        assert_eq!(VoteThreshold::SuperMajorityApprove.approved(300, 100, 400, 900), true);
+        assert_eq!(VoteThreshold::SuperMajorityApprove.approved(300, 100, 100, 900), false);
+        assert_eq!(VoteThreshold::SuperMajorityAgainst.approved(40, 50, 90, 210), true);
+        assert_eq!(VoteThreshold::SuperMajorityAgainst.approved(20, 50, 70, 210), false);
+        assert_eq!(VoteThreshold::SimpleMajority.approved(51, 50, 101, 210), true);
+        assert_eq!(VoteThreshold::SimpleMajority.approved(50, 50, 100, 210), false);
+        assert_eq!(VoteThreshold::SimpleMajority.approved(0, 0, 0, 210), false);
+    }
+
+    #[test]
+    fn low_turnout_should_not_pass_supermajority_approve_proposal() {
+        use runtime_support::StorageValue;
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            assert_ok!(Governance::set_category_rules(Origin::ROOT, governance::ProposalKind::Signaling, governance::CategoryRules {
+                min_turnout: Permill::from_percent(0),
+                approval: Permill::from_percent(50),
+                threshold: vote_threshold::VoteThreshold::SuperMajorityApprove,
+            }));
+            // stand in for a large electorate that stays home
+            <balances::TotalIssuance<Test>>::put(1000);
+
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, false));
+            assert_ok!(submit_vote(other_public, hash, true));
+            finish_voting(hash);
+            assert_eq!(Governance::proposal_of(hash).unwrap().outcome, Some(governance::ProposalOutcome::Rejected));
+        });
+    }
+
+    #[test]
+    fn conviction_should_multiply_vote_weight() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            let conviction_vote = governance::Vote { choices: vec![governance::AYE], conviction: governance::Conviction::Locked3x };
+            assert_ok!(Governance::submit_vote(Origin::signed(public), index_of(hash), conviction_vote, 8));
+            assert_ok!(submit_vote(other_public, hash, false));
+            // the 8 locked, times 3, outweighs 20
+            assert_eq!(Governance::tally(hash), vec![24, 20]);
+            finish_voting(hash);
+            assert_eq!(Governance::proposal_of(hash).unwrap().outcome, Some(governance::ProposalOutcome::Passed));
+        });
+    }
+
+    #[test]
+    fn conviction_should_not_count_towards_quorum() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            assert_ok!(Governance::set_category_rules(Origin::ROOT, governance::ProposalKind::Signaling, governance::CategoryRules {
+                min_turnout: Permill::from_percent(50),
+                approval: Permill::from_percent(50),
+                threshold: vote_threshold::VoteThreshold::SimpleMajority,
+            }));
+
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            // 8 * 6 would clear the 15 of the 30 held outside the treasury
+            // needed for quorum, but 8 does not
+            let conviction_vote = governance::Vote { choices: vec![governance::AYE], conviction: governance::Conviction::Locked6x };
+            assert_ok!(Governance::submit_vote(Origin::signed(public), index_of(hash), conviction_vote, 8));
+            finish_voting(hash);
+            assert_eq!(Governance::tally_of(hash), vec![48, 0]);
+            assert_eq!(Governance::proposal_of(hash).unwrap().outcome, Some(governance::ProposalOutcome::NoQuorum));
+        });
+    }
+
+    #[test]
+    fn conviction_vote_should_lock_until_unlocked() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            let conviction_vote = governance::Vote { choices: vec![governance::AYE], conviction: governance::Conviction::Locked2x };
+            assert_eq!(Governance::submit_vote(Origin::signed(public), index_of(hash), conviction_vote.clone(), 0),
+                       Err("Conviction votes must lock balance"));
+            assert_eq!(Governance::submit_vote(Origin::signed(public), index_of(hash), vote(true), 5),
+                       Err("Only conviction votes lock balance"));
+            assert_ok!(Governance::submit_vote(Origin::signed(public), index_of(hash), conviction_vote, 5));
+            assert_ok!(submit_vote(other_public, hash, true));
+
+            // the locked balance is reserved as soon as the vote is cast, until
+            // two lock periods after voting ends at block 13, and the rest of
+            // the voter's free balance is left alone
+            let lock = governance::LockReason::Vote(hash);
+            assert_eq!(Governance::locks_of(public), vec![(lock, 5, Some(33))]);
+            assert_eq!(Balances::free_balance(&public), 4);
+            assert_eq!(Balances::reserved_balance(&public), 6);
+            assert_eq!(Governance::stake_of(&public), 9);
+            assert_eq!(Governance::locks_of(other_public), vec![]);
+
+            // the locked balance is counted with its conviction
+            finish_voting(hash);
+            assert_eq!(Governance::tally_of(hash), vec![30, 0]);
+            assert_eq!(Governance::locks_of(public), vec![(lock, 5, Some(33))]);
+            assert_eq!(Balances::free_balance(&public), 5);
+            assert_eq!(Balances::reserved_balance(&public), 5);
+            assert_eq!(Governance::locks_of(other_public), vec![]);
+
+            System::set_block_number(32);
+            assert_eq!(Governance::unlock(Origin::signed(public)), Err("Lock has not expired"));
+            System::set_block_number(33);
+            assert_ok!(Governance::unlock(Origin::signed(public)));
+            assert_eq!(Governance::locks_of(public), vec![]);
+            assert_eq!(Balances::free_balance(&public), 10);
+            assert_eq!(Balances::reserved_balance(&public), 0);
+            assert_eq!(Governance::unlock(Origin::signed(public)), Err("Account is not locked"));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::Unlocked(public)));
+        });
+    }
+
+    #[test]
+    fn conviction_lock_should_follow_vote() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            let (other_title, other_proposal) = (b"Other", b"Other proposal");
+            let other_hash = build_proposal_hash(other_public, other_proposal, 1);
+            assert_ok!(propose(other_public, other_title, other_proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(advance_proposal(other_public, other_hash));
+            let locked_vote = |conviction| governance::Vote { choices: vec![governance::AYE], conviction: conviction };
+
+            // the same balance backs both votes, so only the larger is reserved
+            assert_ok!(Governance::submit_vote(Origin::signed(public), index_of(hash), locked_vote(governance::Conviction::Locked2x), 6));
+            assert_ok!(Governance::submit_vote(Origin::signed(public), index_of(other_hash), locked_vote(governance::Conviction::Locked3x), 4));
+            assert_eq!(Governance::locked_balance(&public), 6);
+            assert_eq!(Balances::reserved_balance(&public), 7);
+
+            // changing a vote replaces its lock
+            assert_ok!(Governance::change_vote(Origin::signed(public), index_of(hash), locked_vote(governance::Conviction::Locked2x), 3));
+            assert_eq!(Governance::locked_balance(&public), 4);
+            assert_eq!(Balances::reserved_balance(&public), 5);
+            assert_eq!(Governance::tally(hash), vec![6, 0]);
+
+            // retracting a vote releases its lock
+            assert_ok!(Governance::retract_vote(Origin::signed(public), index_of(other_hash)));
+            assert_eq!(Governance::locks_of(public), vec![(governance::LockReason::Vote(hash), 3, Some(33))]);
+            assert_eq!(Balances::reserved_balance(&public), 4);
+
+            // and so does a veto
+            assert_ok!(Governance::veto(Origin::ROOT, index_of(hash)));
+            assert_eq!(Governance::locks_of(public), vec![]);
+            assert_eq!(Balances::free_balance(&public), 10);
+            assert_eq!(Balances::reserved_balance(&public), 0);
+        });
+    }
+
+    #[test]
+    fn conviction_lock_should_leave_existential_deposit_and_fee() {
+        with_externalities(&mut new_test_ext_with_fees(2, 1), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            let conviction_vote = governance::Vote { choices: vec![governance::AYE], conviction: governance::Conviction::Locked2x };
+
+            // locking 8 of the 9 free would leave less than the existential
+            // deposit and the fee to unlock with
+            assert_eq!(Governance::submit_vote(Origin::signed(public), index_of(hash), conviction_vote.clone(), 8),
+                       Err("Balance too low to lock"));
+            assert_eq!(Governance::submit_vote(Origin::signed(public), index_of(hash), conviction_vote.clone(), 9),
+                       Err("Balance too low to lock"));
+            assert_eq!(Governance::locks_of(public), vec![]);
+            assert_eq!(Balances::free_balance(&public), 9);
+            assert_eq!(Governance::delegate(Origin::signed(other_public), public, governance::Conviction::Locked2x, 20, None),
+                       Err("Balance too low to lock"));
+            assert_eq!(Governance::delegation_of((other_public, None)), None);
+
+            assert_ok!(Governance::submit_vote(Origin::signed(public), index_of(hash), conviction_vote, 6));
+            assert_eq!(Balances::free_balance(&public), 3);
+            assert_eq!(Balances::reserved_balance(&public), 7);
+            assert_eq!(Governance::tally(hash), vec![12, 0]);
+        });
+    }
+
+    #[test]
+    fn propose_poll_should_validate_choices() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let (title, proposal) = generate_proposal();
+            assert_eq!(propose_poll(public, title, proposal, &[b"only"], governance::VotingScheme::SingleChoice), Err("Proposal must offer at least two choices"));
+            assert_eq!(propose_poll(public, title, proposal, &[b"one", b""], governance::VotingScheme::SingleChoice), Err("Choices must not be empty"));
+            assert_eq!(Governance::create_proposal(Origin::signed(public), title.to_vec(), note_contents(public, proposal),
+                                                   governance::ProposalCategory::Funding(1, public),
+                                                   vec![b"one".to_vec(), b"two".to_vec()],
+                                                   governance::VotingScheme::SingleChoice, false),
+                       Err("Only signaling proposals may have choices"));
+            assert_eq!(Governance::create_proposal(Origin::signed(public), title.to_vec(), note_contents(public, proposal),
+                                                   governance::ProposalCategory::Signaling, vec![],
+                                                   governance::VotingScheme::Approval, false),
+                       Err("Only proposals with choices may use this voting scheme"));
+            assert_ok!(propose_poll(public, title, proposal, &[b"one", b"two"], governance::VotingScheme::SingleChoice));
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_eq!(Governance::proposal_of(hash).unwrap().choice_count(), 2);
+        });
+    }
+
+    #[test]
+    fn poll_should_report_winning_choice() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose_poll(public, title, proposal, &[b"low", b"medium", b"high"], governance::VotingScheme::SingleChoice));
+            assert_ok!(advance_proposal(public, hash));
+            assert_eq!(submit_choice(public, hash, 3), Err("Invalid choice"));
+            assert_ok!(submit_choice(public, hash, 0));
+            assert_ok!(submit_choice(other_public, hash, 2));
+            assert_eq!(Governance::tally(hash), vec![9, 0, 20]);
+            finish_voting(hash);
+            let record = Governance::proposal_of(hash).unwrap();
+            assert_eq!(record.outcome, Some(governance::ProposalOutcome::Passed));
+            assert_eq!(record.winning_choice, Some(2));
+            assert_eq!(Governance::tally_of(hash), vec![9, 0, 20]);
+            assert_eq!(System::events()[6].event,
+                       Event::governance(RawEvent::VotingCompleted(hash, governance::ProposalOutcome::Passed, vec![9, 0, 20])));
+        });
+    }
+
+    #[test]
+    fn tied_poll_should_be_rejected() {
+        use runtime_support::StorageMap;
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            // match the proposer's balance after their deposit is reserved
+            <balances::FreeBalance<Test>>::insert(other_public, 9);
+
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose_poll(public, title, proposal, &[b"red", b"blue", b"green"], governance::VotingScheme::SingleChoice));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_choice(public, hash, 0));
+            assert_ok!(submit_choice(other_public, hash, 2));
+            assert_eq!(Governance::tally(hash), vec![9, 0, 9]);
+            finish_voting(hash);
+            let record = Governance::proposal_of(hash).unwrap();
+            assert_eq!(record.outcome, Some(governance::ProposalOutcome::Rejected));
+            assert_eq!(record.winning_choice, None);
+        });
+    }
+
+    #[test]
+    fn approval_poll_should_count_every_approved_choice() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose_poll(public, title, proposal, &[b"red", b"blue", b"green"], governance::VotingScheme::Approval));
+            assert_ok!(advance_proposal(public, hash));
+            assert_eq!(submit_choices(public, hash, &[]), Err("Vote must make a choice"));
+            assert_eq!(submit_choices(public, hash, &[0, 0]), Err("Vote must not repeat a choice"));
+            assert_eq!(submit_choices(public, hash, &[0, 3]), Err("Invalid choice"));
+            assert_ok!(submit_choices(public, hash, &[0, 1]));
+            assert_ok!(submit_choices(other_public, hash, &[1, 2]));
+            assert_eq!(Governance::tally(hash), vec![9, 29, 20]);
+            finish_voting(hash);
+            let record = Governance::proposal_of(hash).unwrap();
+            assert_eq!(record.outcome, Some(governance::ProposalOutcome::Passed));
+            assert_eq!(record.winning_choice, Some(1));
+            assert_eq!(Governance::rounds_of(hash), Vec::<Vec<u64>>::new());
+        });
+    }
+
+    #[test]
+    fn single_choice_poll_should_reject_several_choices() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose_poll(public, title, proposal, &[b"red", b"blue"], governance::VotingScheme::SingleChoice));
+            assert_ok!(advance_proposal(public, hash));
+            assert_eq!(submit_choices(public, hash, &[0, 1]), Err("Vote must make exactly one choice"));
+        });
+    }
+
+    #[test]
+    fn ranked_choice_poll_should_eliminate_until_majority() {
+        use runtime_support::StorageMap;
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let third_public = H256::from(3);
+            <balances::FreeBalance<Test>>::insert(third_public, 15);
+
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose_poll(public, title, proposal, &[b"red", b"blue", b"green"], governance::VotingScheme::RankedChoice));
+            assert_ok!(advance_proposal(public, hash));
+            assert_eq!(submit_choices(public, hash, &[1, 1]), Err("Vote must not repeat a choice"));
+            assert_ok!(submit_choices(public, hash, &[1, 2]));
+            assert_ok!(submit_choices(other_public, hash, &[0]));
+            assert_ok!(submit_choices(third_public, hash, &[2, 1]));
+            // red leads on first preferences, but blue's votes transfer to green
+            assert_eq!(Governance::tally(hash), vec![20, 0, 24]);
+            finish_voting(hash);
+            let record = Governance::proposal_of(hash).unwrap();
+            assert_eq!(record.outcome, Some(governance::ProposalOutcome::Passed));
+            assert_eq!(record.winning_choice, Some(2));
+            assert_eq!(Governance::rounds_of(hash), vec![vec![20, 9, 15], vec![20, 0, 24]]);
+        });
+    }
+
+    #[test]
+    fn quadratic_poll_should_count_square_root_of_credits() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            // credits cannot be measured against the total issuance
+            assert_ok!(Governance::set_category_rules(Origin::ROOT, governance::ProposalKind::Signaling, governance::CategoryRules {
+                min_turnout: Permill::from_percent(50),
+                approval: Permill::from_percent(50),
+                threshold: vote_threshold::VoteThreshold::SuperMajorityApprove,
+            }));
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_eq!(Governance::create_proposal(Origin::signed(public), title.to_vec(), note_contents(public, proposal),
+                                                   governance::ProposalCategory::Funding(1, public), vec![],
+                                                   governance::VotingScheme::Quadratic, false),
+                       Err("Only signaling proposals may vote quadratically"));
+            assert_ok!(propose_poll(public, title, proposal, &[b"yes", b"no"], governance::VotingScheme::Quadratic));
+            assert_ok!(advance_proposal(public, hash));
+            let vote_for = |choice| governance::Vote { choices: vec![choice], conviction: governance::Conviction::None };
+            assert_eq!(submit_choice(public, hash, 0), Err("Quadratic votes must commit credits"));
+            assert_eq!(Governance::submit_quadratic_vote(Origin::signed(public), index_of(hash), vote_for(0), 0), Err("Vote must commit credits"));
+            assert_eq!(Governance::submit_quadratic_vote(Origin::signed(other_public), index_of(hash), vote_for(1), 21), Err("Voter's balance too low"));
+            let conviction_vote = governance::Vote { choices: vec![0], conviction: governance::Conviction::Locked2x };
+            assert_eq!(Governance::submit_quadratic_vote(Origin::signed(public), index_of(hash), conviction_vote.clone(), 4),
+                       Err("Quadratic votes cannot carry conviction"));
+            assert_ok!(Governance::submit_quadratic_vote(Origin::signed(public), index_of(hash), vote_for(0), 4));
+            assert_eq!(Governance::change_vote(Origin::signed(public), index_of(hash), conviction_vote, 4),
+                       Err("Quadratic votes cannot carry conviction"));
+            assert_ok!(Governance::submit_quadratic_vote(Origin::signed(other_public), index_of(hash), vote_for(1), 16));
+            assert_eq!(Balances::reserved_balance(&other_public), 16);
+            assert_eq!(Balances::free_balance(&other_public), 4);
+            assert_eq!(Governance::tally(hash), vec![2, 4]);
+
+            finish_voting(hash);
+            let record = Governance::proposal_of(hash).unwrap();
+            assert_eq!(record.outcome, Some(governance::ProposalOutcome::Passed));
+            assert_eq!(record.winning_choice, Some(1));
+            assert_eq!(Governance::locks_of(public), vec![]);
+            assert_eq!(Balances::reserved_balance(&other_public), 0);
+            assert_eq!(Balances::free_balance(&other_public), 20);
+        });
+    }
+
+    #[test]
+    fn secret_ballot_should_count_revealed_votes() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose_secret(public, title, proposal));
+            assert_ok!(advance_proposal(public, hash));
+            assert_eq!(submit_vote(public, hash, true), Err("Votes on a secret ballot must be committed"));
+            assert_ok!(Governance::commit_vote(Origin::signed(public), index_of(hash), commitment(public, false, b"first")));
+            // a commitment may be replaced without reserving another deposit
+            assert_ok!(Governance::commit_vote(Origin::signed(public), index_of(hash), commitment(public, true, b"salt")));
+            assert_ok!(Governance::commit_vote(Origin::signed(other_public), index_of(hash), commitment(other_public, false, b"pepper")));
+            assert_eq!(Balances::reserved_balance(&public), 2);
+            assert_eq!(Governance::vote_of((hash, public)), None);
+            assert_eq!(Governance::reveal_vote(Origin::signed(public), index_of(hash), vote(true), b"salt".to_vec(), 0),
+                       Err("Proposal not in reveal stage"));
+
+            finish_voting(hash);
+            assert_eq!(Governance::proposal_of(hash).unwrap().stage, governance::ProposalStage::Revealing);
+            assert_eq!(System::events().last().unwrap().event, Event::governance(RawEvent::RevealStarted(hash)));
+            assert_eq!(Governance::reveal_vote(Origin::signed(public), index_of(hash), vote(false), b"salt".to_vec(), 0),
+                       Err("Vote does not match commitment"));
+            assert_ok!(Governance::reveal_vote(Origin::signed(public), index_of(hash), vote(true), b"salt".to_vec(), 0));
+            assert_eq!(Governance::reveal_vote(Origin::signed(public), index_of(hash), vote(true), b"salt".to_vec(), 0),
+                       Err("Vote already revealed"));
+            assert_eq!(Balances::reserved_balance(&public), 1);
+
+            // the other voter never reveals, so their vote is not counted
+            System::set_block_number(23);
+            assert_ok!(Governance::end_block(23));
+            let record = Governance::proposal_of(hash).unwrap();
+            assert_eq!(record.stage, governance::ProposalStage::Completed);
+            assert_eq!(record.outcome, Some(governance::ProposalOutcome::Passed));
+            assert_eq!(Governance::tally_of(hash), vec![9, 0]);
+            assert_eq!(Balances::reserved_balance(&other_public), 0);
+            assert_eq!(Balances::free_balance(&other_public), 19);
+            assert_eq!(Governance::pot(), 101);
+        });
+    }
+
+    #[test]
+    fn copied_commitment_should_not_be_revealed() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose_secret(public, title, proposal));
+            assert_ok!(advance_proposal(public, hash));
+            let published = commitment(public, true, b"salt");
+            assert_ok!(Governance::commit_vote(Origin::signed(public), index_of(hash), published));
+            assert_ok!(Governance::commit_vote(Origin::signed(other_public), index_of(hash), published));
+
+            finish_voting(hash);
+            assert_ok!(Governance::reveal_vote(Origin::signed(public), index_of(hash), vote(true), b"salt".to_vec(), 0));
+            // replaying the revealed vote and salt does not open the copy
+            assert_eq!(Governance::reveal_vote(Origin::signed(other_public), index_of(hash), vote(true), b"salt".to_vec(), 0),
+                       Err("Vote does not match commitment"));
+            assert_eq!(Governance::vote_of((hash, other_public)), None);
+        });
+    }
+
+    #[test]
+    fn delegate_should_prevent_cycles() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let third_public = H256::from(3);
+            assert_eq!(Governance::delegate(Origin::signed(public), public, governance::Conviction::None, 0, None),
+                       Err("Cannot delegate to self"));
+            assert_ok!(Governance::delegate(Origin::signed(public), other_public, governance::Conviction::None, 0, None));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::Delegated(public, other_public, None)));
+            assert_ok!(Governance::delegate(Origin::signed(other_public), third_public, governance::Conviction::None, 0, None));
+            assert_eq!(Governance::delegate(Origin::signed(third_public), public, governance::Conviction::None, 0, None),
+                       Err("Delegation would create a cycle"));
+
+            // re-delegating moves the delegator to its new delegate
+            assert_ok!(Governance::delegate(Origin::signed(public), third_public, governance::Conviction::None, 0, None));
+            assert_eq!(Governance::delegators_of((other_public, None)), vec![]);
+            assert_eq!(Governance::delegators_of((third_public, None)), vec![other_public, public]);
+
+            assert_ok!(Governance::undelegate(Origin::signed(public), None));
+            assert_eq!(Governance::delegation_of((public, None)), None);
+            assert_eq!(Governance::delegators_of((third_public, None)), vec![other_public]);
+            assert_eq!(Governance::undelegate(Origin::signed(public), None), Err("Account is not delegating"));
+        });
+    }
+
+    #[test]
+    fn delegated_stake_should_follow_delegate_vote() {
+        use runtime_support::StorageMap;
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let third_public = H256::from(3);
+            let fourth_public = H256::from(4);
+            <balances::FreeBalance<Test>>::insert(third_public, 15);
+            <balances::FreeBalance<Test>>::insert(fourth_public, 5);
+            assert_ok!(Governance::delegate(Origin::signed(third_public), other_public, governance::Conviction::Locked2x, 10, None));
+            assert_ok!(Governance::delegate(Origin::signed(fourth_public), third_public, governance::Conviction::None, 0, None));
+
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            assert_ok!(submit_vote(other_public, hash, false));
+            // third's 10 locked, times 2, and fourth's 5 count with other
+            assert_eq!(Governance::tally(hash), vec![9, 45]);
+
+            // a delegator that votes directly is counted with its own vote
+            assert_ok!(submit_vote(fourth_public, hash, true));
+            assert_eq!(Governance::tally(hash), vec![14, 40]);
+
+            // the delegation holds its lock for as long as it stands
+            let lock = governance::LockReason::Delegation(None);
+            finish_voting(hash);
+            assert_eq!(Governance::locks_of(third_public), vec![(lock, 10, None)]);
+            assert_eq!(Balances::reserved_balance(&third_public), 10);
+            assert_eq!(Balances::free_balance(&third_public), 5);
+            assert_eq!(Governance::locks_of(fourth_public), vec![]);
+
+            // and for two lock periods once it is removed
+            let now = System::block_number();
+            assert_ok!(Governance::undelegate(Origin::signed(third_public), None));
+            assert_eq!(Governance::locks_of(third_public), vec![(lock, 10, Some(now + 20))]);
+            assert_eq!(Governance::unlock(Origin::signed(third_public)), Err("Lock has not expired"));
+            System::set_block_number(now + 20);
+            assert_ok!(Governance::unlock(Origin::signed(third_public)));
+            assert_eq!(Balances::free_balance(&third_public), 15);
+        });
+    }
+
+    #[test]
+    fn delegation_for_kind_should_override_default() {
+        use runtime_support::StorageMap;
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let public = get_test_key();
+            let other_public = get_other_key();
+            let third_public = H256::from(3);
+            <balances::FreeBalance<Test>>::insert(third_public, 15);
+            let signaling = Some(governance::ProposalKind::Signaling);
+            assert_ok!(Governance::delegate(Origin::signed(third_public), other_public, governance::Conviction::None, 0, None));
+            assert_ok!(Governance::delegate(Origin::signed(third_public), public, governance::Conviction::None, 0, signaling));
+            assert_eq!(Governance::delegate_for(&third_public, governance::ProposalKind::Funding), Some((other_public, governance::Conviction::None, 0)));
+            assert_eq!(Governance::delegate_for(&third_public, governance::ProposalKind::Signaling), Some((public, governance::Conviction::None, 0)));
+
+            // other -> public by default, so public may not delegate funding
+            // votes back through third's default delegate
+            assert_ok!(Governance::delegate(Origin::signed(other_public), public, governance::Conviction::None, 0, None));
+            assert_eq!(Governance::delegate(Origin::signed(public), third_public, governance::Conviction::None, 0, Some(governance::ProposalKind::Funding)),
+                       Err("Delegation would create a cycle"));
+
+            let (title, proposal) = generate_proposal();
+            let hash = build_proposal_hash(public, &proposal, 0);
+            assert_ok!(propose(public, title, proposal, governance::ProposalCategory::Signaling));
+            assert_ok!(advance_proposal(public, hash));
+            assert_ok!(submit_vote(public, hash, true));
+            assert_ok!(submit_vote(other_public, hash, false));
+            assert_eq!(Governance::tally(hash), vec![24, 20]);
+
+            // without its signaling delegate, third falls back to its default
+            assert_ok!(Governance::undelegate(Origin::signed(third_public), signaling));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::governance(RawEvent::Undelegated(third_public, signaling)));
+            assert_eq!(Governance::tally(hash), vec![9, 35]);
+        });
+    }
+}